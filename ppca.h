@@ -0,0 +1,72 @@
+/*
+ * C bindings for `ppca_rs`. Link against the static library built by
+ * `cargo build --release` (`target/release/libppca_rs.a`).
+ *
+ * All matrices are dense, row-major arrays of `double`.
+ */
+
+#ifndef PPCA_H
+#define PPCA_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* An opaque handle to a PPCA model. */
+typedef struct PPCAModel PPCAModel;
+
+/*
+ * Creates a new model. `transform` is an `output_size x state_size` matrix and `mean`
+ * has `output_size` entries. `isotropic_noise` is the standard deviation of the noise.
+ * Returns NULL on invalid input: NULL pointers, a noise which is not positive and
+ * finite, sizes whose product overflows or non-finite parameters. Free the model with
+ * `ppca_model_free`.
+ */
+PPCAModel *ppca_model_new(double isotropic_noise, const double *transform,
+                          const double *mean, size_t output_size,
+                          size_t state_size);
+
+/* Frees a model created by this library. Passing NULL is a no-op. */
+void ppca_model_free(PPCAModel *model);
+
+/*
+ * Writes the log-probability of each of the `n_rows` samples in `data` into `out`
+ * (`n_rows` entries). Non-finite values in `data` are treated as masked. Returns 0 on
+ * success and -1 on error.
+ */
+int32_t ppca_model_llk(const PPCAModel *model, const double *data, size_t n_rows,
+                       size_t n_cols, double *out);
+
+/*
+ * Writes the smoothed samples into `out` (`n_rows * n_cols` entries). An entry of
+ * `data` is observed if the corresponding entry of `mask` is non-zero. Returns 0 on
+ * success and -1 on error.
+ */
+int32_t ppca_model_smooth(const PPCAModel *model, const double *data,
+                          const uint8_t *mask, size_t n_rows, size_t n_cols,
+                          double *out);
+
+/*
+ * Serializes the model as a null-terminated JSON string into `out_buf`. Returns the
+ * length of the JSON (without the null byte), or -1 on error. Nothing is written if
+ * `buf_len` is not enough to hold the JSON and the null byte, so pass a NULL buffer
+ * first to query the size.
+ */
+int64_t ppca_model_serialize_json(const PPCAModel *model, char *out_buf,
+                                  size_t buf_len);
+
+/*
+ * Creates a model from `len` bytes of JSON produced by `ppca_model_serialize_json`.
+ * Returns NULL if the JSON is invalid or does not describe a valid model. Free the
+ * model with `ppca_model_free`.
+ */
+PPCAModel *ppca_model_deserialize_json(const char *json, size_t len);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* PPCA_H */
@@ -0,0 +1,10 @@
+"""
+The bindings under `Py`-prefixed names, e.g., for code which imports both these
+classes and pure Python ones of the same name.
+"""
+
+from .ppca_rs import Dataset as PyDataset
+from .ppca_rs import PPCAMix as PyPPCAMix
+from .ppca_rs import PPCAModel as PyPPCAModel
+
+__all__ = ["PyDataset", "PyPPCAMix", "PyPPCAModel"]
@@ -1,4 +1,4 @@
-from typing import List, Optional
+from typing import List, Optional, Tuple, overload
 
 import numpy as np
 
@@ -27,6 +27,17 @@ class Dataset:
         Returns the size of each sample in this dataset, if the dataset is not empty.
         Else, returns `None`.
         """
+    def with_mask_probability(self, mask_probability: float, seed: int) -> Dataset:
+        """
+        Returns a dataset where each value is additionally masked with probability
+        `mask_probability`. Use this to benchmark imputation on complete data.
+        """
+    def drop_missing(self) -> Dataset:
+        """Returns a dataset with only the samples that have no masked values."""
+    def n_fully_observed(self) -> int:
+        """Returns the number of samples with no masked values."""
+    def n_has_missing(self) -> int:
+        """Returns the number of samples with at least one masked value."""
 
 class InferredMasked:
     """
@@ -107,6 +118,8 @@ class PPCAModel:
     """The linear transformation from hidden state space to output space."""
     isotropic_noise: float
     """The standard deviation of the noise in the output space."""
+    noise_variance: float
+    """The variance of the noise in the output space (`isotropic_noise ** 2`)."""
     mean: np.ndarray
     """Then center of mass of the distribution in the output space."""
     singular_values: np.ndarray
@@ -119,7 +132,10 @@ class PPCAModel:
     state_size: int
     """The number of hidden values for this model."""
     n_parameters: int
-    """The total number of parameters involved in training (used for information criteria)."""
+    """
+    The total number of free parameters involved in training (used for information
+    criteria). This discounts the rotational invariance of the hidden state.
+    """
 
     @staticmethod
     def load(b: bytes) -> PPCAModel:
@@ -156,11 +172,18 @@ class PPCAModel:
         Infers the hidden components for each sample in the dataset. Use this method for
         fine-grain control on the properties you want to extract from the model.
         """
+    @overload
     def smooth(self, dataset: Dataset) -> Dataset:
         """
         Filters a dataset of samples, removing noise from the extant samples and
         inferring the missing samples.
         """
+    @overload
+    def smooth(self, dataset: np.ndarray) -> np.ndarray:
+        """
+        Like `smooth` for a `Dataset`, but on a 2D array with `np.nan` marking the
+        missing values, returning a 2D array.
+        """
     def extrapolate(self, dataset: Dataset) -> Dataset:
         """Extrapolates the missing values with the most probable values."""
     def iterate(self, dataset: Dataset) -> PPCAModel:
@@ -168,6 +191,12 @@ class PPCAModel:
         Makes one iteration of the EM algorithm for the PPCA over an observed dataset,
         returning the improved model.
         """
+    def fit(self, dataset: Dataset, n_iters: int) -> Tuple[PPCAModel, List[float]]:
+        """
+        Makes `n_iters` iterations of the EM algorithm starting from this model. Returns
+        the trained model and the log-probability of the dataset before each iteration
+        and after the last one.
+        """
     def to_canonical(self) -> PPCAModel:
         """
         Returns a canonical version of this model. This does not alter the log-probablility
@@ -261,6 +290,8 @@ class PPCAMix:
 
     output_size: int
     """The number of features for this model."""
+    n_components: int
+    """The number of PPCA models in the mixture."""
     state_sizes: List[int]
     """The number of hidden values for each PPCA model in the mixture."""
     n_parameters: int
@@ -282,6 +313,15 @@ class PPCAMix:
         Creates an uninformed random model to seed the trainment. All constituent models
         will have the same state size.
         """
+    @staticmethod
+    def new_with_state_size(
+        dataset: Dataset, n_components: int, state_size: int, seed: int
+    ) -> PPCAMix:
+        """
+        Creates a mixture from a dataset, with k-means++ seeding for the means and the
+        principal components of the whole dataset for the transforms, followed by one
+        iteration of the EM algorithm. The initialization is deterministic given `seed`.
+        """
     def __repr__(self) -> str: ...
     def llk(self, dataset: Dataset) -> float:
         """
@@ -299,23 +339,73 @@ class PPCAMix:
         Bernoulli (coin-toss) distribution of proability `mask_prob` of erasing the
         generated value.
         """
+    def hard_cluster_assignments(self, dataset: Dataset) -> List[int]:
+        """Returns the most probable component of each sample."""
+    def evaluate_on_grid(
+        self,
+        x_range: Tuple[float, float],
+        y_range: Tuple[float, float],
+        n_points: int,
+    ) -> Tuple[np.ndarray, np.ndarray, np.ndarray]:
+        """
+        Evaluates the log-density of a 2D mixture on a regular grid of `n_points` by
+        `n_points` points, returning `(x_grid, y_grid, log_density_grid)` in the same
+        layout as `numpy.meshgrid`. Use this for contour plots, e.g., with
+        `matplotlib.pyplot.contourf`.
+        """
     def infer(self, dataset: Dataset) -> InferredMaskedMix:
         """
         Infers the hidden components for each sample in the dataset. Use this method for
         fine-grain control on the properties you want to extract from the model.
         """
+    @overload
     def smooth(self, dataset: Dataset) -> Dataset:
         """
         Filters a dataset of samples, removing noise from the extant samples and
         inferring the missing samples.
         """
+    @overload
+    def smooth(self, dataset: np.ndarray) -> np.ndarray:
+        """
+        Like `smooth` for a `Dataset`, but on a 2D array with `np.nan` marking the
+        missing values, returning a 2D array.
+        """
     def extrapolate(self, dataset: Dataset) -> Dataset:
         """Extrapolates the missing values with the most probable values."""
+    def impute_missing(self, dataset: Dataset) -> Dataset:
+        """
+        Fills in the missing values with their posterior mean, keeping the observed
+        values exactly as they are.
+        """
     def iterate(self, dataset: Dataset) -> PPCAModel:
         """
         Makes one iteration of the EM algorithm for the PPCA mixture model over an
         observed dataset, returning a improved model.
         """
+    def fit(self, dataset: Dataset, n_iters: int) -> Tuple[PPCAMix, List[float]]:
+        """
+        Makes `n_iters` iterations of the EM algorithm starting from this model. Returns
+        the trained model and the log-probability of the dataset before each iteration
+        and after the last one.
+        """
+    def fit_with_restarts(
+        self, dataset: Dataset, n_restarts: int, max_iter: int, tol: float, seed: int
+    ) -> PPCAMix:
+        """
+        Fits a mixture with the same state sizes as this one from `n_restarts` random
+        initializations, seeded from `seed`, and returns the one with the highest
+        log-probability. Each run stops after `max_iter` iterations or when the
+        log-probability improves by less than `tol`.
+        """
+    def reorder_components_by_mean(self) -> PPCAMix:
+        """
+        Sorts the components by the lexicographic order of their means, giving a
+        canonical labeling of the components.
+        """
+    def reorder_components_by_weight(self) -> PPCAMix:
+        """
+        Sorts the components from the largest to the smallest weight.
+        """
     def to_canonical(self) -> PPCAModel:
         """
         Returns a canonical version of this model. This does not alter the log-probablility
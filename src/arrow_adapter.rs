@@ -0,0 +1,87 @@
+use arrow2::array::{Array, PrimitiveArray};
+use arrow2::chunk::Chunk;
+use arrow2::error::Error as ArrowError;
+use bit_vec::BitVec;
+
+use crate::ppca_model::{Dataset, MaskedSample};
+use crate::utils::Mask;
+
+impl Dataset {
+    /// Converts this dataset into an Arrow chunk with one `Float64` column per output
+    /// dimension, using `null` for masked values. The output size has to be given
+    /// explicitly because an empty dataset does not know its own. Sample weights are
+    /// not exported.
+    pub fn to_arrow(&self, output_size: usize) -> Chunk<Box<dyn Array>> {
+        let rows = self
+            .data
+            .iter()
+            .map(|sample| {
+                assert_eq!(
+                    sample.mask().0.len(),
+                    output_size,
+                    "sample has wrong output size"
+                );
+                (sample.data_vector(), sample.mask())
+            })
+            .collect::<Vec<_>>();
+
+        let columns = (0..output_size)
+            .map(|dimension| {
+                let column = rows
+                    .iter()
+                    .map(|(data, mask)| {
+                        if mask.0[dimension] {
+                            Some(data[dimension])
+                        } else {
+                            None
+                        }
+                    })
+                    .collect::<Vec<_>>();
+                Box::new(PrimitiveArray::<f64>::from(column)) as Box<dyn Array>
+            })
+            .collect::<Vec<_>>();
+
+        Chunk::new(columns)
+    }
+
+    /// Creates a dataset from an Arrow chunk where each column is an output dimension
+    /// of type `Float64`. Null values become masked values.
+    pub fn from_arrow(chunk: &Chunk<Box<dyn Array>>) -> Result<Dataset, ArrowError> {
+        let columns = chunk
+            .arrays()
+            .iter()
+            .map(|array| {
+                array
+                    .as_any()
+                    .downcast_ref::<PrimitiveArray<f64>>()
+                    .ok_or_else(|| {
+                        ArrowError::InvalidArgumentError(format!(
+                            "expected a Float64 column, got {:?}",
+                            array.data_type()
+                        ))
+                    })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok((0..chunk.len())
+            .map(|row| {
+                let data = columns
+                    .iter()
+                    .map(|column| {
+                        if column.is_valid(row) {
+                            column.value(row)
+                        } else {
+                            0.0
+                        }
+                    })
+                    .collect::<Vec<_>>()
+                    .into();
+                let mask = columns
+                    .iter()
+                    .map(|column| column.is_valid(row))
+                    .collect::<BitVec>();
+                MaskedSample::new(data, Mask(mask))
+            })
+            .collect())
+    }
+}
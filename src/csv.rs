@@ -0,0 +1,95 @@
+use nalgebra::{DMatrix, DVector};
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use crate::error::CsvError;
+
+/// Reads a headerless CSV file of numbers as a matrix, one CSV row per matrix row.
+/// Blank lines are ignored.
+pub(crate) fn read_csv_matrix(path: &Path) -> Result<DMatrix<f64>, CsvError> {
+    let contents = fs::read_to_string(path)?;
+    let mut rows = vec![];
+
+    for (line_idx, line) in contents.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let row = line
+            .split(',')
+            .map(|field| {
+                let field = field.trim();
+                field.parse::<f64>().map_err(|_| CsvError::Parse {
+                    line: line_idx + 1,
+                    field: field.to_owned(),
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if let Some(first) = rows.first().map(Vec::len) {
+            if row.len() != first {
+                return Err(CsvError::RaggedRow {
+                    line: line_idx + 1,
+                    expected: first,
+                    found: row.len(),
+                });
+            }
+        }
+
+        rows.push(row);
+    }
+
+    let ncols = rows.first().map(Vec::len).unwrap_or(0);
+    Ok(DMatrix::from_row_iterator(
+        rows.len(),
+        ncols,
+        rows.into_iter().flatten(),
+    ))
+}
+
+/// Reads a CSV file holding a single row or a single column of numbers as a vector.
+/// `parameter` names the vector in errors.
+pub(crate) fn read_csv_vector(
+    path: &Path,
+    parameter: &'static str,
+) -> Result<DVector<f64>, CsvError> {
+    let matrix = read_csv_matrix(path)?;
+    if matrix.nrows() > 1 && matrix.ncols() > 1 {
+        return Err(CsvError::UnexpectedShape {
+            parameter,
+            nrows: matrix.nrows(),
+            ncols: matrix.ncols(),
+        });
+    }
+
+    Ok(DVector::from_iterator(matrix.len(), matrix.iter().copied()))
+}
+
+/// Reads a CSV file holding a single number.
+pub(crate) fn read_csv_scalar(path: &Path, parameter: &'static str) -> Result<f64, CsvError> {
+    let matrix = read_csv_matrix(path)?;
+    if matrix.shape() != (1, 1) {
+        return Err(CsvError::UnexpectedShape {
+            parameter,
+            nrows: matrix.nrows(),
+            ncols: matrix.ncols(),
+        });
+    }
+
+    Ok(matrix[(0, 0)])
+}
+
+/// Writes a matrix as a headerless CSV file, one matrix row per CSV row, as read by
+/// `read_csv_matrix`. Numbers are written with enough digits to be read back exactly.
+pub(crate) fn write_csv_matrix(path: &Path, matrix: &DMatrix<f64>) -> Result<(), CsvError> {
+    let mut writer = BufWriter::new(File::create(path)?);
+
+    for row in matrix.row_iter() {
+        let line = row.iter().map(f64::to_string).collect::<Vec<_>>().join(",");
+        writeln!(writer, "{line}")?;
+    }
+
+    writer.flush()?;
+    Ok(())
+}
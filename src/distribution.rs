@@ -0,0 +1,120 @@
+use nalgebra::DVector;
+
+use crate::mix::PPCAMix;
+use crate::ppca_model::{Dataset, PPCAModel};
+
+/// The operations shared by `PPCAModel` and `PPCAMix`, for code that treats both
+/// uniformly, e.g., through a `&dyn PPCADistribution`. Each method is the inherent
+/// method of the same name. Use `PPCAModel::as_mix` to go the other way.
+pub trait PPCADistribution {
+    /// The log-likelihood of each sample.
+    fn llks(&self, dataset: &Dataset) -> DVector<f64>;
+    /// The expected value of each sample in the output space, given its observed values.
+    fn smooth(&self, dataset: &Dataset) -> Dataset;
+    /// Like `smooth`, but keeping the observed values as they are.
+    fn extrapolate(&self, dataset: &Dataset) -> Dataset;
+    /// Draws `dataset_size` samples, masking each value with probability `mask_prob`.
+    fn sample(&self, dataset_size: usize, mask_prob: f64) -> Dataset;
+    /// The number of free parameters.
+    fn n_parameters(&self) -> usize;
+}
+
+impl PPCADistribution for PPCAModel {
+    fn llks(&self, dataset: &Dataset) -> DVector<f64> {
+        PPCAModel::llks(self, dataset)
+    }
+
+    fn smooth(&self, dataset: &Dataset) -> Dataset {
+        PPCAModel::smooth(self, dataset)
+    }
+
+    fn extrapolate(&self, dataset: &Dataset) -> Dataset {
+        PPCAModel::extrapolate(self, dataset)
+    }
+
+    fn sample(&self, dataset_size: usize, mask_prob: f64) -> Dataset {
+        PPCAModel::sample(self, dataset_size, mask_prob)
+    }
+
+    fn n_parameters(&self) -> usize {
+        PPCAModel::n_parameters(self)
+    }
+}
+
+impl PPCADistribution for PPCAMix {
+    fn llks(&self, dataset: &Dataset) -> DVector<f64> {
+        PPCAMix::llks(self, dataset)
+    }
+
+    fn smooth(&self, dataset: &Dataset) -> Dataset {
+        PPCAMix::smooth(self, dataset)
+    }
+
+    fn extrapolate(&self, dataset: &Dataset) -> Dataset {
+        PPCAMix::extrapolate(self, dataset)
+    }
+
+    fn sample(&self, dataset_size: usize, mask_prob: f64) -> Dataset {
+        PPCAMix::sample(self, dataset_size, mask_prob)
+    }
+
+    fn n_parameters(&self) -> usize {
+        PPCAMix::n_parameters(self)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use nalgebra::{dmatrix, dvector};
+
+    use super::*;
+
+    fn toy_model() -> PPCAModel {
+        PPCAModel::new(
+            0.1,
+            dmatrix![
+                1.0, 1.0, 0.0;
+                1.0, 0.0, 1.0;
+            ]
+            .transpose(),
+            dvector![0.0, 1.0, 0.0],
+        )
+    }
+
+    #[test]
+    fn test_as_mix() {
+        let model = toy_model();
+        let mix = model.as_mix();
+        let dataset = model.sample(100, 0.2);
+
+        assert_eq!(mix.n_components(), 1);
+        approx::assert_relative_eq!(mix.llk(&dataset), model.llk(&dataset), max_relative = 1e-12);
+        approx::assert_relative_eq!(
+            mix.smooth(&dataset).to_dense_matrix(0.0),
+            model.smooth(&dataset).to_dense_matrix(0.0),
+            max_relative = 1e-12
+        );
+        assert_eq!(
+            PPCADistribution::n_parameters(&mix),
+            PPCADistribution::n_parameters(&model)
+        );
+    }
+
+    #[test]
+    fn test_trait_object() {
+        let model = toy_model();
+        let mix = model.as_mix();
+        let distributions: [&dyn PPCADistribution; 2] = [&model, &mix];
+        let dataset = model.sample(50, 0.2);
+
+        let llks = distributions
+            .iter()
+            .map(|distribution| distribution.llks(&dataset))
+            .collect::<Vec<_>>();
+        approx::assert_relative_eq!(llks[0], llks[1], max_relative = 1e-12);
+        for distribution in distributions {
+            assert_eq!(distribution.sample(10, 0.0).len(), 10);
+            assert_eq!(distribution.extrapolate(&dataset).len(), dataset.len());
+        }
+    }
+}
@@ -0,0 +1,233 @@
+use std::fmt;
+use std::io;
+
+/// Why a model is malformed, as reported by `PPCAModel::validate` and
+/// `PPCAMix::validate`, e.g., after deserializing it from an untrusted source.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ValidationError {
+    /// The transform and the mean disagree on the output size.
+    OutputSizeMismatch {
+        transform_rows: usize,
+        mean_len: usize,
+    },
+    /// The isotropic noise standard deviation is zero, negative or NaN.
+    NonPositiveNoise(f64),
+    /// A parameter contains NaN or infinite values.
+    NonFinite(&'static str),
+    /// A mixture has no components.
+    NoComponents,
+    /// A mixture has a different number of components and weights.
+    WeightCountMismatch {
+        n_components: usize,
+        n_weights: usize,
+    },
+    /// The weights of a mixture do not sum to one.
+    WeightsNotNormalized(f64),
+    /// A component of a mixture has a different output size than the first one.
+    ComponentOutputSizeMismatch {
+        component: usize,
+        expected: usize,
+        found: usize,
+    },
+    /// A component of a mixture is malformed.
+    InvalidComponent {
+        component: usize,
+        error: Box<ValidationError>,
+    },
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValidationError::OutputSizeMismatch {
+                transform_rows,
+                mean_len,
+            } => write!(
+                f,
+                "transform has {transform_rows} rows but mean has {mean_len} entries"
+            ),
+            ValidationError::NonPositiveNoise(noise) => {
+                write!(f, "noise standard deviation must be positive, got {noise}")
+            }
+            ValidationError::NonFinite(parameter) => {
+                write!(f, "{parameter} contains NaN or infinite values")
+            }
+            ValidationError::NoComponents => write!(f, "mixture has no components"),
+            ValidationError::WeightCountMismatch {
+                n_components,
+                n_weights,
+            } => write!(
+                f,
+                "mixture has {n_components} components but {n_weights} weights"
+            ),
+            ValidationError::WeightsNotNormalized(total) => {
+                write!(f, "mixture weights sum to {total} instead of 1")
+            }
+            ValidationError::ComponentOutputSizeMismatch {
+                component,
+                expected,
+                found,
+            } => write!(
+                f,
+                "component {component} has output size {found} instead of {expected}"
+            ),
+            ValidationError::InvalidComponent { component, error } => {
+                write!(f, "component {component} is invalid: {error}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
+/// Why a dataset is malformed, as reported by `Dataset::validate`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DatasetError {
+    /// A sample has a different dimension than the first one (or than the model).
+    DimensionMismatch {
+        sample: usize,
+        expected: usize,
+        found: usize,
+    },
+    /// A sample has a negative or NaN weight.
+    InvalidWeight { sample: usize, weight: f64 },
+    /// The weights of a non-empty dataset do not sum to a positive finite value.
+    InvalidTotalWeight(f64),
+}
+
+impl fmt::Display for DatasetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DatasetError::DimensionMismatch {
+                sample,
+                expected,
+                found,
+            } => write!(
+                f,
+                "sample {sample} has dimension {found} instead of {expected}"
+            ),
+            DatasetError::InvalidWeight { sample, weight } => {
+                write!(f, "sample {sample} has invalid weight {weight}")
+            }
+            DatasetError::InvalidTotalWeight(total) => {
+                write!(
+                    f,
+                    "weights must sum to a positive finite value, got {total}"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for DatasetError {}
+
+/// Why a model could not be read from (or written to) CSV files, as reported by
+/// `PPCAModel::from_csv` and `PPCAMix::from_csv`.
+#[derive(Debug)]
+pub enum CsvError {
+    /// A file could not be read or written.
+    Io(io::Error),
+    /// A field is not a number.
+    Parse { line: usize, field: String },
+    /// A row has a different number of fields than the first one.
+    RaggedRow {
+        line: usize,
+        expected: usize,
+        found: usize,
+    },
+    /// A file that should hold a vector (or a single value) holds a matrix of another
+    /// shape.
+    UnexpectedShape {
+        parameter: &'static str,
+        nrows: usize,
+        ncols: usize,
+    },
+    /// The parameters were read but do not make a valid model.
+    Invalid(ValidationError),
+}
+
+impl fmt::Display for CsvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CsvError::Io(error) => write!(f, "i/o error: {error}"),
+            CsvError::Parse { line, field } => {
+                write!(f, "line {line}: cannot parse {field:?} as a number")
+            }
+            CsvError::RaggedRow {
+                line,
+                expected,
+                found,
+            } => write!(f, "line {line} has {found} fields instead of {expected}"),
+            CsvError::UnexpectedShape {
+                parameter,
+                nrows,
+                ncols,
+            } => write!(f, "{parameter} has unexpected shape {nrows}x{ncols}"),
+            CsvError::Invalid(error) => write!(f, "invalid model: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for CsvError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            CsvError::Io(error) => Some(error),
+            CsvError::Invalid(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for CsvError {
+    fn from(error: io::Error) -> CsvError {
+        CsvError::Io(error)
+    }
+}
+
+impl From<ValidationError> for CsvError {
+    fn from(error: ValidationError) -> CsvError {
+        CsvError::Invalid(error)
+    }
+}
+
+/// An output dimension out of bounds, as reported by `Dataset::project_to_dims`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IndexError {
+    pub index: usize,
+    pub output_size: usize,
+}
+
+impl fmt::Display for IndexError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "dimension {} is out of bounds for output size {}",
+            self.index, self.output_size
+        )
+    }
+}
+
+impl std::error::Error for IndexError {}
+
+/// An affine transform `x' = A x + b` whose matrix `A` is not a multiple of an orthogonal
+/// matrix, as reported by `PPCAModel::apply_affine_transform` and
+/// `PPCAModel::apply_inverse_affine_transform`. Such a transform would make the noise
+/// anisotropic, which a PPCA model cannot represent.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AffineTransformError {
+    /// The relative deviation `‖Aᵀ A - c I‖_F / ‖c I‖_F` from a multiple `c I` of the
+    /// identity.
+    pub deviation: f64,
+}
+
+impl fmt::Display for AffineTransformError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "affine transform is not a multiple of an orthogonal matrix (relative deviation {})",
+            self.deviation
+        )
+    }
+}
+
+impl std::error::Error for AffineTransformError {}
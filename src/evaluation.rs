@@ -0,0 +1,341 @@
+use nalgebra::DVector;
+use std::collections::HashMap;
+
+use crate::mix::PPCAMix;
+use crate::ppca_model::{Dataset, PPCAModel};
+
+/// A dataset together with a ground-truth integer label (e.g., a cluster) per sample.
+#[derive(Debug, Clone)]
+pub struct LabeledDataset {
+    dataset: Dataset,
+    labels: Vec<usize>,
+}
+
+impl Dataset {
+    /// Associates a ground-truth label with each sample of this dataset.
+    pub fn with_labels(&self, labels: Vec<usize>) -> LabeledDataset {
+        assert_eq!(
+            labels.len(),
+            self.len(),
+            "need exactly one label per sample"
+        );
+        LabeledDataset {
+            dataset: self.clone(),
+            labels,
+        }
+    }
+}
+
+/// Measures of agreement between a clustering and the ground-truth labels. All of them
+/// are `1.0` for a perfect clustering, up to relabeling.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ClusteringMetrics {
+    /// The Rand index adjusted for chance: around `0.0` for random assignments.
+    pub adjusted_rand_index: f64,
+    /// The mutual information between clusters and labels, normalized by the arithmetic
+    /// mean of their entropies.
+    pub normalized_mutual_information: f64,
+    /// The fraction of samples which belong to the most common label of their cluster.
+    pub purity: f64,
+}
+
+impl LabeledDataset {
+    pub fn dataset(&self) -> &Dataset {
+        &self.dataset
+    }
+
+    pub fn labels(&self) -> &[usize] {
+        &self.labels
+    }
+
+    /// Compares cluster assignments (e.g., from `PPCAMix::hard_cluster_assignments`)
+    /// with the labels of this dataset. Sample weights are not taken into account.
+    pub fn evaluate_clustering(&self, assignments: &[usize]) -> ClusteringMetrics {
+        assert_eq!(
+            assignments.len(),
+            self.labels.len(),
+            "need exactly one assignment per sample"
+        );
+        let n_samples = self.labels.len() as f64;
+
+        let mut contingency = HashMap::<(usize, usize), f64>::new();
+        let mut label_counts = HashMap::<usize, f64>::new();
+        let mut cluster_counts = HashMap::<usize, f64>::new();
+        for (&label, &cluster) in self.labels.iter().zip(assignments) {
+            *contingency.entry((label, cluster)).or_default() += 1.0;
+            *label_counts.entry(label).or_default() += 1.0;
+            *cluster_counts.entry(cluster).or_default() += 1.0;
+        }
+
+        let pairs = |count: &f64| count * (count - 1.0) / 2.0;
+        let index = contingency.values().map(pairs).sum::<f64>();
+        let label_pairs = label_counts.values().map(pairs).sum::<f64>();
+        let cluster_pairs = cluster_counts.values().map(pairs).sum::<f64>();
+        let expected_index = label_pairs * cluster_pairs / pairs(&n_samples);
+        let max_index = (label_pairs + cluster_pairs) / 2.0;
+        let adjusted_rand_index = if max_index == expected_index {
+            // Both clusterings are trivial (e.g., a single cluster each).
+            1.0
+        } else {
+            (index - expected_index) / (max_index - expected_index)
+        };
+
+        let entropy = |counts: &HashMap<usize, f64>| {
+            -counts
+                .values()
+                .map(|&count| count / n_samples * (count / n_samples).ln())
+                .sum::<f64>()
+        };
+        let mutual_information = contingency
+            .iter()
+            .map(|((label, cluster), &count)| {
+                count / n_samples
+                    * (count * n_samples / (label_counts[label] * cluster_counts[cluster])).ln()
+            })
+            .sum::<f64>();
+        let mean_entropy = (entropy(&label_counts) + entropy(&cluster_counts)) / 2.0;
+        let normalized_mutual_information = if mean_entropy == 0.0 {
+            1.0
+        } else {
+            mutual_information / mean_entropy
+        };
+
+        let mut majorities = HashMap::<usize, f64>::new();
+        for (&(_, cluster), &count) in &contingency {
+            let majority = majorities.entry(cluster).or_default();
+            *majority = majority.max(count);
+        }
+        let purity = majorities.values().sum::<f64>() / n_samples;
+
+        ClusteringMetrics {
+            adjusted_rand_index,
+            normalized_mutual_information,
+            purity,
+        }
+    }
+}
+
+/// Goodness of fit of a `PPCAModel` on a (test) dataset, as computed by
+/// `PPCAModel::evaluate`. Totals and means take the sample weights into account.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ModelEvaluation {
+    /// The log-likelihood of the whole dataset.
+    pub total_llk: f64,
+    /// The log-likelihood per sample.
+    pub mean_llk: f64,
+    /// The log-likelihood per observed value.
+    pub mean_llk_per_dim: f64,
+    /// The Bayesian information criterion, `n_parameters ln(n_samples) - 2 total_llk`.
+    pub bic: f64,
+    /// The Akaike information criterion, `2 n_parameters - 2 total_llk`.
+    pub aic: f64,
+    /// The squared distance per sample between the observed values and their
+    /// reconstruction by `PPCAModel::smooth`.
+    pub mean_reconstruction_error: f64,
+}
+
+impl PPCAModel {
+    /// Computes the usual goodness of fit metrics of this model on a dataset at once.
+    pub fn evaluate(&self, test: &Dataset) -> ModelEvaluation {
+        let total_weight = test.weights.iter().sum::<f64>();
+        let total_observed = test
+            .iter()
+            .zip(&test.weights)
+            .map(|(sample, &weight)| weight * sample.n_observed() as f64)
+            .sum::<f64>();
+        let total_reconstruction_error = test
+            .iter()
+            .zip(&test.weights)
+            .map(|(sample, &weight)| {
+                let error = sample.data_vector() - self.smooth_one(sample).data_vector();
+                weight * sample.mask().mask(&error).norm_squared()
+            })
+            .sum::<f64>();
+
+        let total_llk = self.llk(test);
+        let n_parameters = self.n_parameters() as f64;
+
+        ModelEvaluation {
+            total_llk,
+            mean_llk: total_llk / total_weight,
+            mean_llk_per_dim: total_llk / total_observed,
+            bic: n_parameters * total_weight.ln() - 2.0 * total_llk,
+            aic: 2.0 * n_parameters - 2.0 * total_llk,
+            mean_reconstruction_error: total_reconstruction_error / total_weight,
+        }
+    }
+}
+
+/// Goodness of fit of a `PPCAMix` on a (test) dataset, as computed by
+/// `PPCAMix::evaluate`. Totals and means take the sample weights into account.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MixtureEvaluation {
+    /// The log-likelihood of the whole dataset.
+    pub total_llk: f64,
+    /// The log-likelihood per sample.
+    pub mean_llk: f64,
+    /// The Bayesian information criterion, `n_parameters ln(n_samples) - 2 total_llk`.
+    pub bic: f64,
+    /// The Akaike information criterion, `2 n_parameters - 2 total_llk`.
+    pub aic: f64,
+    /// The integrated completed likelihood criterion: the BIC penalized by twice the
+    /// total entropy of the cluster assignments.
+    pub icl: f64,
+    /// The exponential of the entropy of the mixture weights: the number of components
+    /// for uniform weights, and less for unbalanced ones.
+    pub effective_n_components: f64,
+    /// The entropy of the cluster posterior per sample: zero when the components are
+    /// perfectly separated on the dataset.
+    pub mean_assignment_entropy: f64,
+}
+
+impl PPCAMix {
+    /// Computes the usual goodness of fit metrics of this mixture on a dataset at once.
+    pub fn evaluate(&self, test: &Dataset) -> MixtureEvaluation {
+        let total_weight = test.weights.iter().sum::<f64>();
+        let total_assignment_entropy = self
+            .compute_responsibilities(test)
+            .entropy()
+            .dot(&DVector::from(test.weights.clone()));
+        let weight_entropy = -self
+            .log_weights()
+            .iter()
+            .map(|&log_weight| log_weight.exp() * log_weight)
+            .filter(|term| !term.is_nan())
+            .sum::<f64>();
+
+        let total_llk = self.llk(test);
+        let n_parameters = self.n_parameters() as f64;
+        let bic = n_parameters * total_weight.ln() - 2.0 * total_llk;
+
+        MixtureEvaluation {
+            total_llk,
+            mean_llk: total_llk / total_weight,
+            bic,
+            aic: 2.0 * n_parameters - 2.0 * total_llk,
+            icl: bic + 2.0 * total_assignment_entropy,
+            effective_n_components: weight_entropy.exp(),
+            mean_assignment_entropy: total_assignment_entropy / total_weight,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::ppca_model::MaskedSample;
+    use nalgebra::{dmatrix, dvector};
+
+    fn labeled(labels: Vec<usize>) -> LabeledDataset {
+        labels
+            .iter()
+            .map(|_| MaskedSample::unmasked(dvector![0.0]))
+            .collect::<Dataset>()
+            .with_labels(labels)
+    }
+
+    #[test]
+    fn test_perfect_clustering() {
+        let metrics = labeled(vec![0, 0, 1, 1, 2]).evaluate_clustering(&[2, 2, 0, 0, 1]);
+        approx::assert_relative_eq!(metrics.adjusted_rand_index, 1.0);
+        approx::assert_relative_eq!(metrics.normalized_mutual_information, 1.0);
+        approx::assert_relative_eq!(metrics.purity, 1.0);
+    }
+
+    #[test]
+    fn test_evaluate_clustering() {
+        let metrics = labeled(vec![0, 0, 0, 1, 1, 1]).evaluate_clustering(&[0, 0, 1, 1, 2, 2]);
+        approx::assert_relative_eq!(metrics.adjusted_rand_index, 0.242424, epsilon = 1e-6);
+        approx::assert_relative_eq!(
+            metrics.normalized_mutual_information,
+            0.515804,
+            epsilon = 1e-6
+        );
+        approx::assert_relative_eq!(metrics.purity, 5.0 / 6.0);
+    }
+
+    #[test]
+    fn test_evaluate_model() {
+        let model = PPCAModel::new(
+            0.1,
+            dmatrix![1.0, 1.0, 0.0].transpose(),
+            dvector![0.0, 1.0, 0.0],
+        );
+        let test = model.sample(200, 0.0);
+        let evaluation = model.evaluate(&test);
+
+        approx::assert_relative_eq!(evaluation.total_llk, model.llk(&test), max_relative = 1e-10);
+        approx::assert_relative_eq!(evaluation.total_llk, evaluation.mean_llk * 200.0);
+        approx::assert_relative_eq!(evaluation.mean_llk_per_dim, evaluation.mean_llk / 3.0);
+        approx::assert_relative_eq!(
+            evaluation.bic,
+            model.n_parameters() as f64 * 200f64.ln() - 2.0 * evaluation.total_llk
+        );
+        approx::assert_relative_eq!(
+            evaluation.aic,
+            2.0 * model.n_parameters() as f64 - 2.0 * evaluation.total_llk
+        );
+        // The reconstruction misses about the noise in the two directions orthogonal to
+        // the loading, i.e., `2 σ²` per sample.
+        approx::assert_relative_eq!(
+            evaluation.mean_reconstruction_error,
+            0.02,
+            max_relative = 0.3
+        );
+    }
+
+    #[test]
+    fn test_evaluate_mixture() {
+        let mix = PPCAMix::new(
+            vec![
+                PPCAModel::new(
+                    0.1,
+                    dmatrix![1.0, 1.0, 0.0].transpose(),
+                    dvector![0.0, 1.0, 0.0],
+                ),
+                PPCAModel::new(
+                    0.5,
+                    dmatrix![0.0, 1.0, 1.0].transpose(),
+                    dvector![1.0, 0.0, 0.0],
+                ),
+            ],
+            dvector![0.25f64.ln(), 0.75f64.ln()],
+        );
+        let test = mix.sample(300, 0.1);
+        let evaluation = mix.evaluate(&test);
+
+        approx::assert_relative_eq!(evaluation.total_llk, mix.llk(&test), max_relative = 1e-10);
+        approx::assert_relative_eq!(evaluation.total_llk, evaluation.mean_llk * 300.0);
+        approx::assert_relative_eq!(
+            evaluation.bic,
+            mix.n_parameters() as f64 * 300f64.ln() - 2.0 * evaluation.total_llk
+        );
+        approx::assert_relative_eq!(
+            evaluation.aic,
+            2.0 * mix.n_parameters() as f64 - 2.0 * evaluation.total_llk
+        );
+
+        let entropies = mix.compute_responsibilities(&test).entropy();
+        approx::assert_relative_eq!(
+            evaluation.mean_assignment_entropy,
+            entropies.mean(),
+            max_relative = 1e-10
+        );
+        approx::assert_relative_eq!(
+            evaluation.icl,
+            evaluation.bic + 2.0 * entropies.sum(),
+            max_relative = 1e-10
+        );
+        approx::assert_relative_eq!(
+            evaluation.effective_n_components,
+            (-(0.25 * 0.25f64.ln() + 0.75 * 0.75f64.ln())).exp(),
+            max_relative = 1e-10
+        );
+
+        // An empty test set has no log-likelihood, but must not panic.
+        let empty = mix.evaluate(&Dataset::new(vec![]));
+        assert_eq!(empty.total_llk, 0.0);
+        assert!(empty.mean_llk.is_nan());
+    }
+}
@@ -0,0 +1,132 @@
+use std::time::Instant;
+
+/// Progress of a fit after one EM iteration, as sent by `PPCAMix::fit_monitor`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct IterationReport {
+    /// The number of the iteration, starting at 1.
+    pub iter: usize,
+    /// The log-likelihood after the iteration.
+    pub llk: f64,
+    /// The improvement of the log-likelihood during the iteration.
+    pub delta_llk: f64,
+    /// The time since the start of the fit, in seconds.
+    pub elapsed_secs: f64,
+}
+
+/// Configuration of the EM fitting in `PPCAModel::fit` and `PPCAMix::fit`, built with
+/// chained setters, e.g., `PPCAFitConfig::default().max_iter(200).tol(1e-6)`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PPCAFitConfig {
+    pub(crate) max_iter: usize,
+    pub(crate) tol: f64,
+    pub(crate) l2_penalty: f64,
+    pub(crate) fix_mean: bool,
+    pub(crate) fix_noise: bool,
+    pub(crate) verbose: bool,
+}
+
+impl Default for PPCAFitConfig {
+    fn default() -> PPCAFitConfig {
+        PPCAFitConfig {
+            max_iter: 100,
+            tol: 1e-6,
+            l2_penalty: 0.0,
+            fix_mean: false,
+            fix_noise: false,
+            verbose: false,
+        }
+    }
+}
+
+impl PPCAFitConfig {
+    /// The maximum number of EM iterations.
+    pub fn max_iter(mut self, max_iter: usize) -> PPCAFitConfig {
+        self.max_iter = max_iter;
+        self
+    }
+
+    /// Stops when the relative improvement of the log-likelihood,
+    /// `(llk_new - llk_old) / |llk_old|`, falls below this value.
+    pub fn tol(mut self, tol: f64) -> PPCAFitConfig {
+        self.tol = tol;
+        self
+    }
+
+    /// Ridge penalty on the rows of the transform, added to the second moment of the
+    /// states in the M-step. Larger values shrink the transform towards zero.
+    pub fn l2_penalty(mut self, l2_penalty: f64) -> PPCAFitConfig {
+        assert!(l2_penalty >= 0.0, "l2 penalty must be non-negative");
+        self.l2_penalty = l2_penalty;
+        self
+    }
+
+    /// Keeps the mean of the initial model instead of learning it.
+    pub fn fix_mean(mut self, fix_mean: bool) -> PPCAFitConfig {
+        self.fix_mean = fix_mean;
+        self
+    }
+
+    /// Keeps the isotropic noise of the initial model instead of learning it.
+    pub fn fix_noise(mut self, fix_noise: bool) -> PPCAFitConfig {
+        self.fix_noise = fix_noise;
+        self
+    }
+
+    /// Logs the log-likelihood after each iteration at the `info` level of the `log`
+    /// crate, for whatever logger the application installs. Use `PPCAMix::fit_monitor`
+    /// to receive the progress programmatically instead.
+    pub fn verbose(mut self, verbose: bool) -> PPCAFitConfig {
+        self.verbose = verbose;
+        self
+    }
+
+    /// Runs the EM loop shared by all models: records the log-likelihood before the first
+    /// iteration and after each one, stopping at `max_iter` iterations or when the
+    /// relative improvement falls below `tol`.
+    pub(crate) fn run<M>(
+        &self,
+        init: M,
+        llk: impl Fn(&M) -> f64,
+        iterate: impl Fn(&M) -> M,
+    ) -> (M, Vec<f64>) {
+        self.run_with_reports(init, llk, iterate, |_| {})
+    }
+
+    /// Like `PPCAFitConfig::run`, but also hands an `IterationReport` to `report` after
+    /// each iteration.
+    pub(crate) fn run_with_reports<M>(
+        &self,
+        init: M,
+        llk: impl Fn(&M) -> f64,
+        iterate: impl Fn(&M) -> M,
+        mut report: impl FnMut(IterationReport),
+    ) -> (M, Vec<f64>) {
+        let start = Instant::now();
+        let mut model = init;
+        let mut llks = vec![llk(&model)];
+
+        for iter in 0..self.max_iter {
+            model = iterate(&model);
+            let new_llk = llk(&model);
+            let old_llk = llks[llks.len() - 1];
+            llks.push(new_llk);
+
+            if self.verbose {
+                log::info!("At iteration {} llk is {}", iter + 1, new_llk);
+            }
+
+            report(IterationReport {
+                iter: iter + 1,
+                llk: new_llk,
+                delta_llk: new_llk - old_llk,
+                elapsed_secs: start.elapsed().as_secs_f64(),
+            });
+
+            if (new_llk - old_llk) / old_llk.abs() < self.tol {
+                break;
+            }
+        }
+
+        (model, llks)
+    }
+}
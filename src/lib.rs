@@ -1,11 +1,31 @@
 // mod dataframe_adapter;
+#[cfg(feature = "arrow")]
+mod arrow_adapter;
+mod csv;
+mod distribution;
+mod error;
+mod evaluation;
+mod fit_config;
+pub mod math;
 mod mix;
 mod output_covariance;
+mod ppca_ffi;
 mod ppca_model;
+#[cfg(feature = "python")]
 mod python_bindings;
 mod utils;
 // mod polars_python_hack;
 
+pub use distribution::PPCADistribution;
+pub use error::{AffineTransformError, CsvError, DatasetError, IndexError, ValidationError};
+pub use evaluation::{ClusteringMetrics, LabeledDataset, MixtureEvaluation, ModelEvaluation};
+pub use fit_config::{IterationReport, PPCAFitConfig};
+pub use mix::{InferredMaskedMix, PPCAMix, Responsibilities};
+pub use ppca_model::{
+    Dataset, EMDiagnostics, InferredMasked, MaskedSample, PPCAModel, SufficientStatistics,
+};
+pub use utils::Mask;
+
 #[cfg(test)]
 mod test {
     use super::*;
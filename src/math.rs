@@ -0,0 +1,271 @@
+//! Numerically stable helpers for probabilistic computations in the log domain.
+
+use nalgebra::DVector;
+
+/// Normalizes a vector of log-probabilities, i.e., calculates `x - ln(Σ exp(x))`
+/// elementwise. The maximum is subtracted before exponentiating, so that this does not
+/// overflow nor underflow to `-inf` for large (negative) inputs.
+pub fn log_softmax(data: DVector<f64>) -> DVector<f64> {
+    let max = data.max();
+    let log_norm = data.iter().map(|&xi| (xi - max).exp()).sum::<f64>().ln();
+    data.map(|xi| xi - max - log_norm)
+}
+
+/// Calculates `ln(Σ exp(x))`. The maximum is subtracted before exponentiating, so that
+/// this does not overflow nor underflow to `-inf` for large (negative) inputs.
+pub fn log_sum_exp(data: DVector<f64>) -> f64 {
+    let max = data.max();
+    let log_norm = data.iter().map(|&xi| (xi - max).exp()).sum::<f64>().ln();
+    max + log_norm
+}
+
+/// Calculates `ln(exp(a) + exp(b))` without overflowing.
+pub(crate) fn log_add_exp(a: f64, b: f64) -> f64 {
+    let (max, min) = if a > b { (a, b) } else { (b, a) };
+    if min == f64::NEG_INFINITY {
+        max
+    } else {
+        max + (min - max).exp().ln_1p()
+    }
+}
+
+/// Calculates the logarithm of the gamma function for positive arguments, using the
+/// Lanczos approximation (accurate to about 15 significant digits).
+pub fn ln_gamma(x: f64) -> f64 {
+    const COEFFICIENTS: [f64; 9] = [
+        0.999_999_999_999_809_9,
+        676.520_368_121_885_1,
+        -1_259.139_216_722_402_8,
+        771.323_428_777_653_1,
+        -176.615_029_162_140_6,
+        12.507_343_278_686_905,
+        -0.138_571_095_265_720_12,
+        9.984_369_578_019_572e-6,
+        1.505_632_735_149_311_6e-7,
+    ];
+
+    if x < 0.5 {
+        // Reflection formula.
+        std::f64::consts::PI.ln() - (std::f64::consts::PI * x).sin().abs().ln() - ln_gamma(1.0 - x)
+    } else {
+        let x = x - 1.0;
+        let t = x + 7.5;
+        let series = COEFFICIENTS[1..]
+            .iter()
+            .enumerate()
+            .map(|(i, &coefficient)| coefficient / (x + i as f64 + 1.0))
+            .sum::<f64>()
+            + COEFFICIENTS[0];
+        0.5 * (2.0 * std::f64::consts::PI).ln() + (x + 0.5) * t.ln() - t + series.ln()
+    }
+}
+
+/// Calculates the regularized upper incomplete gamma function `Q(a, x) = Γ(a, x) / Γ(a)`,
+/// for `a > 0` and `x >= 0`. Uses the series expansion of `P = 1 - Q` for `x < a + 1`
+/// and a continued fraction otherwise, each of which converges fast in its region.
+pub fn regularized_gamma_q(a: f64, x: f64) -> f64 {
+    assert!(a > 0.0, "shape must be positive");
+    assert!(x >= 0.0, "argument must be non-negative");
+    const MAX_ITERATIONS: usize = 1_000;
+    const TINY: f64 = 1e-300;
+
+    if x == 0.0 {
+        return 1.0;
+    }
+
+    let log_prefactor = -x + a * x.ln() - ln_gamma(a);
+
+    if x < a + 1.0 {
+        let mut term = 1.0 / a;
+        let mut sum = term;
+        for n in 1..MAX_ITERATIONS {
+            term *= x / (a + n as f64);
+            sum += term;
+            if term.abs() < sum.abs() * f64::EPSILON {
+                break;
+            }
+        }
+        1.0 - sum * log_prefactor.exp()
+    } else {
+        // Modified Lentz's method.
+        let mut b = x + 1.0 - a;
+        let mut c = 1.0 / TINY;
+        let mut d = 1.0 / b;
+        let mut fraction = d;
+        for n in 1..MAX_ITERATIONS {
+            let an = -(n as f64) * (n as f64 - a);
+            b += 2.0;
+            d = an * d + b;
+            if d.abs() < TINY {
+                d = TINY;
+            }
+            c = b + an / c;
+            if c.abs() < TINY {
+                c = TINY;
+            }
+            d = 1.0 / d;
+            let delta = d * c;
+            fraction *= delta;
+            if (delta - 1.0).abs() < f64::EPSILON {
+                break;
+            }
+        }
+        fraction * log_prefactor.exp()
+    }
+}
+
+/// The survival function `P(X > x)` of a chi-squared variable `X` with `dof` degrees of
+/// freedom, i.e., the p-value of the statistic `x`.
+pub fn chi_squared_sf(x: f64, dof: f64) -> f64 {
+    assert!(dof > 0.0, "degrees of freedom must be positive");
+    if x <= 0.0 {
+        1.0
+    } else {
+        regularized_gamma_q(dof / 2.0, x / 2.0)
+    }
+}
+
+/// The cumulative distribution function `Φ(x)` of the standard normal distribution.
+pub fn standard_normal_cdf(x: f64) -> f64 {
+    // `P(|Z| > |x|)` is the survival function of a chi-squared variable with one degree
+    // of freedom at `x²`.
+    let two_sided_tail = regularized_gamma_q(0.5, x * x / 2.0);
+    if x < 0.0 {
+        two_sided_tail / 2.0
+    } else {
+        1.0 - two_sided_tail / 2.0
+    }
+}
+
+/// The quantile function `Φ⁻¹(p)` of the standard normal distribution, for `0 < p < 1`.
+/// Uses the rational approximation of P. J. Acklam (relative error below `1.2e-9`),
+/// refined by one step of Halley's method.
+pub fn standard_normal_quantile(p: f64) -> f64 {
+    assert!(p > 0.0 && p < 1.0, "probability must be in (0, 1)");
+    const A: [f64; 6] = [
+        -3.969_683_028_665_376e1,
+        2.209_460_984_245_205e2,
+        -2.759_285_104_469_687e2,
+        1.383_577_518_672_69e2,
+        -3.066_479_806_614_716e1,
+        2.506_628_277_459_239,
+    ];
+    const B: [f64; 5] = [
+        -5.447_609_879_822_406e1,
+        1.615_858_368_580_409e2,
+        -1.556_989_798_598_866e2,
+        6.680_131_188_771_972e1,
+        -1.328_068_155_288_572e1,
+    ];
+    const C: [f64; 6] = [
+        -7.784_894_002_430_293e-3,
+        -3.223_964_580_411_365e-1,
+        -2.400_758_277_161_838,
+        -2.549_732_539_343_734,
+        4.374_664_141_464_968,
+        2.938_163_982_698_783,
+    ];
+    const D: [f64; 4] = [
+        7.784_695_709_041_462e-3,
+        3.224_671_290_700_398e-1,
+        2.445_134_137_142_996,
+        3.754_408_661_907_416,
+    ];
+    const P_LOW: f64 = 0.024_25;
+
+    let tail = |q: f64| {
+        (((((C[0] * q + C[1]) * q + C[2]) * q + C[3]) * q + C[4]) * q + C[5])
+            / ((((D[0] * q + D[1]) * q + D[2]) * q + D[3]) * q + 1.0)
+    };
+    let x = if p < P_LOW {
+        tail((-2.0 * p.ln()).sqrt())
+    } else if p > 1.0 - P_LOW {
+        -tail((-2.0 * (1.0 - p).ln()).sqrt())
+    } else {
+        let q = p - 0.5;
+        let r = q * q;
+        (((((A[0] * r + A[1]) * r + A[2]) * r + A[3]) * r + A[4]) * r + A[5]) * q
+            / (((((B[0] * r + B[1]) * r + B[2]) * r + B[3]) * r + B[4]) * r + 1.0)
+    };
+
+    let error = standard_normal_cdf(x) - p;
+    let step = error * (2.0 * std::f64::consts::PI).sqrt() * (x * x / 2.0).exp();
+    x - step / (1.0 + x * step / 2.0)
+}
+
+#[cfg(test)]
+mod test {
+    use nalgebra::dvector;
+
+    use super::*;
+
+    #[test]
+    fn test_log_sum_exp() {
+        approx::assert_relative_eq!(
+            log_sum_exp(dvector![1.0, 2.0, 3.0]),
+            (1f64.exp() + 2f64.exp() + 3f64.exp()).ln()
+        );
+        // Naively, this would be `ln(0)`.
+        approx::assert_relative_eq!(log_sum_exp(dvector![-1000.0, -1000.0]), -1000.0 + 2f64.ln());
+        approx::assert_relative_eq!(
+            log_add_exp(-1000.0, -1000.0),
+            log_sum_exp(dvector![-1000.0, -1000.0])
+        );
+        assert_eq!(log_add_exp(f64::NEG_INFINITY, 1.0), 1.0);
+    }
+
+    #[test]
+    fn test_ln_gamma() {
+        approx::assert_relative_eq!(ln_gamma(5.0), 24f64.ln(), epsilon = 1e-12);
+        approx::assert_relative_eq!(
+            ln_gamma(0.5),
+            std::f64::consts::PI.sqrt().ln(),
+            epsilon = 1e-12
+        );
+        approx::assert_relative_eq!(ln_gamma(0.1), 2.252712651734206, epsilon = 1e-12);
+    }
+
+    #[test]
+    fn test_chi_squared_sf() {
+        // Two degrees of freedom give an exponential distribution.
+        approx::assert_relative_eq!(chi_squared_sf(3.0, 2.0), (-1.5f64).exp(), epsilon = 1e-12);
+        approx::assert_relative_eq!(chi_squared_sf(3.841459, 1.0), 0.05, epsilon = 1e-6);
+        approx::assert_relative_eq!(chi_squared_sf(18.307038, 10.0), 0.05, epsilon = 1e-6);
+        approx::assert_relative_eq!(chi_squared_sf(0.5, 10.0), 0.9999934, epsilon = 1e-6);
+        assert_eq!(chi_squared_sf(0.0, 3.0), 1.0);
+    }
+
+    #[test]
+    fn test_standard_normal_quantile() {
+        approx::assert_relative_eq!(standard_normal_cdf(0.0), 0.5, epsilon = 1e-15);
+        approx::assert_relative_eq!(
+            standard_normal_cdf(-1.0),
+            0.15865525393145707,
+            epsilon = 1e-12
+        );
+        approx::assert_relative_eq!(standard_normal_quantile(0.5), 0.0, epsilon = 1e-15);
+        approx::assert_relative_eq!(
+            standard_normal_quantile(0.975),
+            1.959963984540054,
+            epsilon = 1e-12
+        );
+        approx::assert_relative_eq!(
+            standard_normal_quantile(0.01),
+            -2.3263478740408408,
+            epsilon = 1e-12
+        );
+        for &p in &[1e-10, 0.001, 0.2, 0.7, 0.999] {
+            approx::assert_relative_eq!(
+                standard_normal_cdf(standard_normal_quantile(p)),
+                p,
+                max_relative = 1e-10
+            );
+        }
+    }
+
+    #[test]
+    fn test_log_softmax() {
+        let normalized = log_softmax(dvector![1000.0, 1000.0 + 2f64.ln()]);
+        approx::assert_relative_eq!(normalized.map(f64::exp), dvector![1.0 / 3.0, 2.0 / 3.0]);
+    }
+}
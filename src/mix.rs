@@ -1,23 +1,29 @@
 use nalgebra::{DMatrix, DVector};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 use rand_distr::{Distribution, WeightedIndex};
 use rayon::prelude::*;
 use serde_derive::{Deserialize, Serialize};
+use std::io::{Read, Write};
+use std::path::Path;
+use std::sync::mpsc::{self, Receiver};
+use std::thread::{self, JoinHandle};
 
+use crate::csv::read_csv_vector;
+use crate::error::{CsvError, DatasetError, ValidationError};
+use crate::fit_config::{IterationReport, PPCAFitConfig};
+use crate::math::{chi_squared_sf, log_add_exp, log_softmax, log_sum_exp};
 use crate::ppca_model::{Dataset, InferredMasked, MaskedSample, PPCAModel};
 
-/// Performs Bayesian inference in the log domain.
-fn robust_log_softmax(data: DVector<f64>) -> DVector<f64> {
-    let max = data.max();
-    let log_norm = data.iter().map(|&xi| (xi - max).exp()).sum::<f64>().ln();
-    data.map(|xi| xi - max - log_norm)
-}
-
-/// Performs Bayesian inference in the log domain.
-fn robust_log_softnorm(data: DVector<f64>) -> f64 {
-    let max = data.max();
-    let log_norm = data.iter().map(|&xi| (xi - max).exp()).sum::<f64>().ln();
-    max + log_norm
-}
+/// Standard deviation of the component means in `PPCAMix::generate_synthetic`.
+const SYNTHETIC_MEAN_SCALE: f64 = 10.0;
+/// Tolerance on the sum of the weights in `PPCAMix::validate`.
+const WEIGHT_NORMALIZATION_TOL: f64 = 1e-6;
+/// Maximum number of EM iterations of each fit in `PPCAMix::cross_validate_n_components`.
+const CROSS_VALIDATION_MAX_ITER: usize = 100;
+/// Absolute log-likelihood improvement below which each fit in
+/// `PPCAMix::cross_validate_n_components` stops.
+const CROSS_VALIDATION_TOL: f64 = 1e-4;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PPCAMix {
@@ -46,36 +52,321 @@ impl PPCAMix {
         PPCAMix {
             output_size: unique_sizes[0],
             models,
-            log_weights: robust_log_softmax(log_weights),
+            log_weights: log_softmax(log_weights),
         }
     }
 
     pub fn init(n_models: usize, state_size: usize, dataset: &Dataset) -> PPCAMix {
+        PPCAMix::init_with_rng(n_models, state_size, dataset, &mut rand::thread_rng())
+    }
+
+    /// Like `PPCAMix::init`, but drawing the random components from the supplied random
+    /// number generator, for reproducible initializations.
+    pub fn init_with_rng<R: Rng + ?Sized>(
+        n_models: usize,
+        state_size: usize,
+        dataset: &Dataset,
+        rng: &mut R,
+    ) -> PPCAMix {
         PPCAMix::new(
             (0..n_models)
-                .map(|_| PPCAModel::init(state_size, dataset))
+                .map(|_| PPCAModel::init_with_rng(state_size, dataset, rng))
                 .collect(),
             vec![0.0; n_models].into(),
         )
     }
 
+    /// Creates a random ground-truth mixture with `n_components` equally weighted
+    /// components of the given state sizes and isotropic noise `noise`, and samples
+    /// `n_samples` fully observed points from it. The component means are drawn with a
+    /// standard deviation of `SYNTHETIC_MEAN_SCALE`, so that the components are well
+    /// separated. Everything is derived deterministically from `seed`.
+    pub fn generate_synthetic(
+        output_size: usize,
+        state_sizes: &[usize],
+        n_components: usize,
+        n_samples: usize,
+        noise: f64,
+        seed: u64,
+    ) -> (PPCAMix, Dataset) {
+        assert_eq!(
+            state_sizes.len(),
+            n_components,
+            "need one state size per component"
+        );
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mix = PPCAMix::new(
+            state_sizes
+                .iter()
+                .map(|&state_size| {
+                    PPCAModel::random_with_rng(
+                        output_size,
+                        state_size,
+                        noise,
+                        SYNTHETIC_MEAN_SCALE,
+                        &mut rng,
+                    )
+                })
+                .collect(),
+            vec![0.0; n_components].into(),
+        );
+
+        let index = WeightedIndex::new(vec![1.0; n_components])
+            .expect("can create WeigtedIndex from distribution");
+        let dataset = (0..n_samples)
+            .map(|_| {
+                let model_idx = index.sample(&mut rng);
+                mix.models[model_idx].sample_one_with_rng(0.0, &mut rng)
+            })
+            .collect();
+
+        (mix, dataset)
+    }
+
+    /// Creates a mixture from a dataset, ready to be trained. The component means are
+    /// chosen by k-means++ seeding (missing values are filled with the sample mean for the
+    /// distances), the transforms come from the principal components of the whole
+    /// dataset (see `PPCAModel::from_moments`) and one EM iteration adjusts the noise and
+    /// the weights. The random choices are derived deterministically from `seed`.
+    pub fn new_with_state_size(
+        dataset: &Dataset,
+        n_components: usize,
+        state_size: usize,
+        seed: u64,
+    ) -> PPCAMix {
+        assert!(n_components > 0, "need at least one component");
+        assert!(!dataset.is_empty(), "cannot initialize from empty dataset");
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mean = dataset.sample_mean();
+        let covariance = dataset.sample_covariance();
+        let filled = dataset
+            .data
+            .iter()
+            .map(|sample| sample.mask().choose(&sample.data_vector(), &mean))
+            .collect::<Vec<_>>();
+
+        let first = WeightedIndex::new(&dataset.weights)
+            .expect("can create WeigtedIndex from distribution")
+            .sample(&mut rng);
+        let mut centers = vec![filled[first].clone()];
+        let mut distances = filled
+            .iter()
+            .map(|point| (point - &centers[0]).norm_squared())
+            .collect::<Vec<_>>();
+
+        while centers.len() < n_components {
+            let scores = distances
+                .iter()
+                .zip(&dataset.weights)
+                .map(|(distance, weight)| distance * weight);
+            // All points may already be centers, in which case any will do.
+            let next = match WeightedIndex::new(scores) {
+                Ok(index) => index.sample(&mut rng),
+                Err(_) => rng.gen_range(0..filled.len()),
+            };
+            let center = filled[next].clone();
+            for (distance, point) in distances.iter_mut().zip(&filled) {
+                *distance = distance.min((point - &center).norm_squared());
+            }
+            centers.push(center);
+        }
+
+        let models = centers
+            .into_iter()
+            .map(|center| PPCAModel::from_moments(center, &covariance, state_size))
+            .collect();
+
+        PPCAMix::new(models, vec![0.0; n_components].into()).iterate(dataset)
+    }
+
     pub fn output_size(&self) -> usize {
         self.output_size
     }
 
+    pub fn n_components(&self) -> usize {
+        self.models.len()
+    }
+
+    /// The state size of each component, in the same order as `models`.
     pub fn state_sizes(&self) -> Vec<usize> {
         self.models.iter().map(PPCAModel::state_size).collect()
     }
 
+    pub fn max_state_size(&self) -> usize {
+        self.models
+            .iter()
+            .map(PPCAModel::state_size)
+            .max()
+            .expect("mixture is not empty")
+    }
+
+    pub fn min_state_size(&self) -> usize {
+        self.models
+            .iter()
+            .map(PPCAModel::state_size)
+            .min()
+            .expect("mixture is not empty")
+    }
+
+    /// The parameters of each component plus `n_components - 1` free mixture weights
+    /// (the log-weights are normalized, which removes one degree of freedom).
     pub fn n_parameters(&self) -> usize {
         self.models
             .iter()
             .map(PPCAModel::n_parameters)
             .sum::<usize>()
-            + self.models.len()
+            + self.n_components()
             - 1
     }
 
+    /// The number of identifiable parameters of the mixture, for information criteria:
+    /// `Σ_m (d k_m - k_m (k_m - 1) / 2 + d + 1) + K - 1`, where `d` is the output size,
+    /// `k_m` the state size of component `m` and `K` the number of components. Each
+    /// component discounts the rotations of its hidden state, as in
+    /// `PPCAModel::n_parameters`, so this equals `PPCAMix::n_parameters`.
+    pub fn n_free_parameters(&self) -> usize {
+        self.n_parameters()
+    }
+
+    /// Flattens the parameters of this mixture into a single vector: the
+    /// `PPCAModel::param_vector` of each component, followed by the log-weights of all
+    /// but the last component relative to the last one. The last log-weight is left out
+    /// since the weights are normalized anyway.
+    pub fn param_vector(&self) -> DVector<f64> {
+        let n_components = self.n_components();
+        let last_log_weight = self.log_weights[n_components - 1];
+
+        self.models
+            .iter()
+            .flat_map(|model| model.param_vector().data.as_vec().clone())
+            .chain(
+                self.log_weights
+                    .iter()
+                    .take(n_components - 1)
+                    .map(|&log_weight| log_weight - last_log_weight),
+            )
+            .collect::<Vec<_>>()
+            .into()
+    }
+
+    /// Reconstructs a mixture from the flat vector produced by `PPCAMix::param_vector`.
+    pub fn from_param_vector(
+        output_size: usize,
+        state_sizes: &[usize],
+        params: &DVector<f64>,
+    ) -> PPCAMix {
+        let model_sizes = state_sizes
+            .iter()
+            .map(|state_size| output_size * state_size + output_size + 1)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            params.len(),
+            model_sizes.iter().sum::<usize>() + state_sizes.len() - 1,
+            "parameter vector has wrong size"
+        );
+
+        let mut offset = 0;
+        let models = state_sizes
+            .iter()
+            .zip(&model_sizes)
+            .map(|(&state_size, &model_size)| {
+                let model_params = params.rows(offset, model_size).clone_owned();
+                offset += model_size;
+                PPCAModel::from_param_vector(output_size, state_size, &model_params)
+            })
+            .collect::<Vec<_>>();
+        let log_weights = params
+            .iter()
+            .skip(offset)
+            .copied()
+            .chain([0.0])
+            .collect::<Vec<_>>();
+
+        PPCAMix::new(models, log_weights.into())
+    }
+
+    /// Checks that this mixture is well-formed: it has as many weights as components, at
+    /// least one of them, the weights are normalized, and all components are valid (see
+    /// `PPCAModel::validate`) with the same output size. Use this on mixtures coming
+    /// from untrusted sources.
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        if self.models.is_empty() {
+            return Err(ValidationError::NoComponents);
+        }
+        if self.models.len() != self.log_weights.len() {
+            return Err(ValidationError::WeightCountMismatch {
+                n_components: self.models.len(),
+                n_weights: self.log_weights.len(),
+            });
+        }
+
+        let total_weight = self
+            .log_weights
+            .iter()
+            .map(|log_weight| log_weight.exp())
+            .sum::<f64>();
+        if total_weight.is_nan() || (total_weight - 1.0).abs() >= WEIGHT_NORMALIZATION_TOL {
+            return Err(ValidationError::WeightsNotNormalized(total_weight));
+        }
+
+        for (component, model) in self.models.iter().enumerate() {
+            model
+                .validate()
+                .map_err(|error| ValidationError::InvalidComponent {
+                    component,
+                    error: Box::new(error),
+                })?;
+            if model.output_size() != self.output_size {
+                return Err(ValidationError::ComponentOutputSizeMismatch {
+                    component,
+                    expected: self.output_size,
+                    found: model.output_size(),
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Reads a mixture encoded as JSON, without loading the whole encoding in memory
+    /// first. Wrap files in a `BufReader` for good performance.
+    pub fn from_json_reader<R: Read>(reader: R) -> Result<PPCAMix, serde_json::Error> {
+        serde_json::from_reader(reader)
+    }
+
+    /// Writes this mixture encoded as JSON, as read by `PPCAMix::from_json_reader`. Wrap
+    /// files in a `BufWriter` for good performance. The writer is flushed before
+    /// returning, so that write errors are reported rather than lost on drop.
+    pub fn to_json_writer<W: Write>(&self, mut writer: W) -> Result<(), serde_json::Error> {
+        serde_json::to_writer(&mut writer, self)?;
+        writer.flush().map_err(serde_json::Error::io)
+    }
+
+    /// Reads a mixture from CSV files: one `(loadings, mean, noise)` triple of paths per
+    /// component, in the layout read by `PPCAModel::from_csv`, and a file with the
+    /// weights of the components (not their logs), as a single row or column.
+    pub fn from_csv(
+        component_paths: &[(&Path, &Path, &Path)],
+        weights_path: &Path,
+    ) -> Result<PPCAMix, CsvError> {
+        let models = component_paths
+            .iter()
+            .map(|&(loadings_path, mean_path, noise_path)| {
+                PPCAModel::from_csv(loadings_path, mean_path, noise_path)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let log_weights = read_csv_vector(weights_path, "weights")?.map(f64::ln);
+
+        let mix = PPCAMix {
+            output_size: models.first().map(PPCAModel::output_size).unwrap_or(0),
+            models,
+            log_weights,
+        };
+        mix.validate()?;
+
+        Ok(mix)
+    }
+
     pub fn models(&self) -> &[PPCAModel] {
         &self.models
     }
@@ -84,6 +375,210 @@ impl PPCAMix {
         &self.log_weights
     }
 
+    /// The differential entropy of each component, see `PPCAModel::entropy`.
+    pub fn component_entropies(&self) -> DVector<f64> {
+        self.models
+            .iter()
+            .map(PPCAModel::entropy)
+            .collect::<Vec<_>>()
+            .into()
+    }
+
+    /// The average of the component entropies weighted by the mixture weights. This is a
+    /// lower bound on the entropy of the mixture itself, which has no closed form.
+    pub fn expected_entropy(&self) -> f64 {
+        self.log_weights
+            .iter()
+            .zip(&self.component_entropies())
+            .map(|(&log_weight, &entropy)| log_weight.exp() * entropy)
+            .sum()
+    }
+
+    /// Draws exactly `n_per_component[k]` samples from each component `k`, regardless of
+    /// the mixture weights. Returns the samples, grouped by component, together with the
+    /// index of the component each one was drawn from.
+    pub fn sample_n_per_component(
+        &self,
+        n_per_component: &[usize],
+        mask_probability: f64,
+        seed: u64,
+    ) -> (Dataset, Vec<usize>) {
+        assert_eq!(
+            n_per_component.len(),
+            self.n_components(),
+            "need one sample count per component"
+        );
+        let mut rng = StdRng::seed_from_u64(seed);
+        let labels = n_per_component
+            .iter()
+            .enumerate()
+            .flat_map(|(model_idx, &n_samples)| std::iter::repeat(model_idx).take(n_samples))
+            .collect::<Vec<_>>();
+        let dataset = labels
+            .iter()
+            .map(|&model_idx| {
+                self.models[model_idx].sample_one_with_rng(mask_probability, &mut rng)
+            })
+            .collect();
+
+        (dataset, labels)
+    }
+
+    /// Evaluates the log-density of a mixture with `output_size == 2` on a regular grid of
+    /// `n_points × n_points` points spanning the given ranges, for contour plots. Returns
+    /// the grid coordinates and the log-densities, with `x` varying along the columns and
+    /// `y` along the rows, like `numpy.meshgrid`.
+    pub fn evaluate_on_grid(
+        &self,
+        x_range: (f64, f64),
+        y_range: (f64, f64),
+        n_points: usize,
+    ) -> (DMatrix<f64>, DMatrix<f64>, DMatrix<f64>) {
+        assert_eq!(
+            self.output_size, 2,
+            "can only evaluate 2D mixtures on a grid"
+        );
+        assert!(n_points >= 2, "grid needs at least two points per axis");
+
+        let step = |(start, end): (f64, f64), idx: usize| {
+            start + (end - start) * idx as f64 / (n_points - 1) as f64
+        };
+        let x_grid = DMatrix::from_fn(n_points, n_points, |_, col| step(x_range, col));
+        let y_grid = DMatrix::from_fn(n_points, n_points, |row, _| step(y_range, row));
+        let log_densities = x_grid
+            .iter()
+            .zip(&y_grid)
+            .collect::<Vec<_>>()
+            .into_par_iter()
+            .map(|(&x, &y)| self.llk_single(&MaskedSample::unmasked(DVector::from(vec![x, y]))))
+            .collect::<Vec<_>>();
+
+        (
+            x_grid,
+            y_grid,
+            DMatrix::from_vec(n_points, n_points, log_densities),
+        )
+    }
+
+    /// The `PPCAModel::dimension_importance` of each component, averaged with the
+    /// mixture weights.
+    pub fn dimension_importance(&self) -> DVector<f64> {
+        self.log_weights
+            .iter()
+            .zip(&self.models)
+            .map(|(&log_weight, model)| log_weight.exp() * model.dimension_importance())
+            .fold(DVector::zeros(self.output_size), |total, importance| {
+                total + importance
+            })
+    }
+
+    /// The Jensen lower bound `Σ_k w_k log Z_k ≤ log Σ_k w_k Z_k` on the log partition
+    /// function of the mixture, where `Z_k` is the normalizer of component `k` (see
+    /// `PPCAModel::log_normalizer`).
+    pub fn log_partition_function_lower(&self) -> f64 {
+        self.log_weights
+            .iter()
+            .zip(&self.models)
+            .map(|(&log_weight, model)| log_weight.exp() * model.log_normalizer())
+            .sum()
+    }
+
+    /// The upper bound `max_k log Z_k ≥ log Σ_k w_k Z_k` on the log partition function of
+    /// the mixture, where `Z_k` is the normalizer of component `k` (see
+    /// `PPCAModel::log_normalizer`).
+    pub fn log_partition_function_upper(&self) -> f64 {
+        self.models
+            .iter()
+            .map(PPCAModel::log_normalizer)
+            .fold(f64::NEG_INFINITY, f64::max)
+    }
+
+    /// The matrix of the divergences `KL(model_i || model_j)` between the output
+    /// distributions of each pair of components, see `PPCAModel::kl_divergence`. Note
+    /// that this matrix is not symmetric.
+    pub fn component_pairwise_kl(&self) -> DMatrix<f64> {
+        DMatrix::from_fn(self.n_components(), self.n_components(), |i, j| {
+            if i == j {
+                0.0
+            } else {
+                self.models[i].kl_divergence(&self.models[j])
+            }
+        })
+    }
+
+    /// A Monte Carlo estimate, from `n_mc` samples of each component, of the divergence
+    /// `KL(model_k || mix)` between the output distribution of each component and the
+    /// whole mixture. This measures how poorly a component alone represents the mixture:
+    /// it lies between `0` and `-ln w_k`, the latter reached by components far from all
+    /// the others. Estimates are clamped to be non-negative. The draws are derived
+    /// deterministically from `seed`.
+    pub fn kl_components_to_mixture(&self, n_mc: usize, seed: u64) -> DVector<f64> {
+        assert!(n_mc > 0, "need at least one draw");
+        let mut rng = StdRng::seed_from_u64(seed);
+
+        self.models
+            .iter()
+            .map(|model| {
+                let dataset = (0..n_mc)
+                    .map(|_| model.sample_one_with_rng(0.0, &mut rng))
+                    .collect::<Dataset>();
+                let log_ratio = (model.llks(&dataset) - self.llks(&dataset)).sum() / n_mc as f64;
+                log_ratio.max(0.0)
+            })
+            .collect::<Vec<_>>()
+            .into()
+    }
+
+    /// The mean of the mixture, `Σ_k w_k μ_k`.
+    fn expected_output_mean(&self) -> DVector<f64> {
+        self.log_weights
+            .iter()
+            .zip(&self.models)
+            .map(|(&log_weight, model)| log_weight.exp() * model.mean())
+            .fold(DVector::zeros(self.output_size), |total, mean| total + mean)
+    }
+
+    /// The covariance matrix of the output under the mixture,
+    /// `Σ_k w_k (W_k W_kᵀ + σ_k²I + μ_k μ_kᵀ) - μ μᵀ`, where `μ = Σ_k w_k μ_k`.
+    pub fn expected_output_covariance(&self) -> DMatrix<f64> {
+        let mean = self.expected_output_mean();
+        self.log_weights
+            .iter()
+            .zip(&self.models)
+            .map(|(&log_weight, model)| {
+                log_weight.exp()
+                    * (model.output_covariance().matrix() + model.mean() * model.mean().transpose())
+            })
+            .fold(
+                DMatrix::zeros(self.output_size, self.output_size),
+                |total, covariance| total + covariance,
+            )
+            - &mean * mean.transpose()
+    }
+
+    /// The diagonal of `PPCAMix::expected_output_covariance`, without building the full
+    /// matrix.
+    pub fn expected_output_variance(&self) -> DVector<f64> {
+        let mean = self.expected_output_mean();
+        self.log_weights
+            .iter()
+            .zip(&self.models)
+            .map(|(&log_weight, model)| {
+                let transform = &*model.output_covariance().transform;
+                let variance = transform.map(|w| w.powi(2)).column_sum()
+                    + model.mean().map(|mu| mu.powi(2))
+                    + DVector::repeat(
+                        self.output_size,
+                        model.output_covariance().isotropic_noise.powi(2),
+                    );
+                log_weight.exp() * variance
+            })
+            .fold(DVector::zeros(self.output_size), |total, variance| {
+                total + variance
+            })
+            - mean.map(|mu| mu.powi(2))
+    }
+
     pub fn sample(&self, dataset_size: usize, mask_probability: f64) -> Dataset {
         let index = WeightedIndex::new(self.log_weights.iter().copied().map(f64::exp))
             .expect("can create WeigtedIndex from distribution");
@@ -96,6 +591,44 @@ impl PPCAMix {
             .collect()
     }
 
+    /// Like `PPCAMix::sample`, but reproducible: the samples are derived
+    /// deterministically from `seed`.
+    pub fn sample_with_seed(
+        &self,
+        dataset_size: usize,
+        mask_probability: f64,
+        seed: u64,
+    ) -> Dataset {
+        self.sample_with_rng(
+            dataset_size,
+            mask_probability,
+            &mut StdRng::seed_from_u64(seed),
+        )
+    }
+
+    /// Like `PPCAMix::sample`, but drawing the randomness from `rng`. Since the samples
+    /// are drawn in parallel, `rng` only draws one seed per sample, from which each
+    /// sample is then derived deterministically.
+    pub fn sample_with_rng(
+        &self,
+        dataset_size: usize,
+        mask_probability: f64,
+        rng: &mut impl Rng,
+    ) -> Dataset {
+        let index = WeightedIndex::new(self.log_weights.iter().copied().map(f64::exp))
+            .expect("can create WeigtedIndex from distribution");
+        let seeds = (0..dataset_size).map(|_| rng.gen()).collect::<Vec<u64>>();
+
+        seeds
+            .into_par_iter()
+            .map(|seed| {
+                let mut rng = StdRng::seed_from_u64(seed);
+                let model_idx = index.sample(&mut rng);
+                self.models[model_idx].sample_one_with_rng(mask_probability, &mut rng)
+            })
+            .collect()
+    }
+
     pub(crate) fn llks_one(&self, sample: &MaskedSample) -> DVector<f64> {
         self.models
             .iter()
@@ -104,15 +637,36 @@ impl PPCAMix {
             .into()
     }
 
+    /// Checks that `dataset` is well formed (see `Dataset::validate`) and that its
+    /// samples have the output size of this mixture.
+    pub(crate) fn check_dataset(&self, dataset: &Dataset) -> Result<(), DatasetError> {
+        dataset.validate()?;
+        dataset.validate_dimension(self.output_size)
+    }
+
     pub fn llks(&self, dataset: &Dataset) -> DVector<f64> {
         dataset
             .data
             .par_iter()
-            .map(|sample| robust_log_softnorm(self.llks_one(sample) + &self.log_weights))
+            .map(|sample| log_sum_exp(self.llks_one(sample) + &self.log_weights))
             .collect::<Vec<_>>()
             .into()
     }
 
+    /// Like `PPCAMix::llks`, but returns an error instead of panicking when the dataset is
+    /// malformed or its samples do not have the output size of this mixture.
+    pub fn try_llks(&self, dataset: &Dataset) -> Result<DVector<f64>, DatasetError> {
+        self.check_dataset(dataset)?;
+        Ok(self.llks(dataset))
+    }
+
+    /// Like `PPCAMix::llk`, but returns an error instead of panicking when the dataset is
+    /// malformed or its samples do not have the output size of this mixture.
+    pub fn try_llk(&self, dataset: &Dataset) -> Result<f64, DatasetError> {
+        self.check_dataset(dataset)?;
+        Ok(self.llk(dataset))
+    }
+
     pub fn llk(&self, dataset: &Dataset) -> f64 {
         // Rayon doesn't like to sum empty stuff...
         if dataset.is_empty() {
@@ -124,85 +678,467 @@ impl PPCAMix {
             .par_iter()
             .zip(&dataset.weights)
             .map(|(sample, &weight)| {
-                weight * robust_log_softnorm(self.llks_one(sample) + &self.log_weights)
+                weight * log_sum_exp(self.llks_one(sample) + &self.log_weights)
             })
             .sum::<f64>()
     }
 
+    /// The log-likelihood of a single sample. The component likelihoods are combined on
+    /// the fly, without collecting them into a vector.
+    pub fn llk_single(&self, sample: &MaskedSample) -> f64 {
+        self.models
+            .iter()
+            .zip(&self.log_weights)
+            .map(|(model, &log_weight)| log_weight + model.llk_one(sample))
+            .fold(f64::NEG_INFINITY, log_add_exp)
+    }
+
+    /// The log-likelihood of the observed dimensions of each sample, with the unobserved
+    /// dimensions marginalized out. See `PPCAModel::conditional_llk`.
+    pub fn conditional_llk(&self, dataset: &Dataset) -> DVector<f64> {
+        self.llks(dataset)
+    }
+
+    /// Tests whether `model_b` fits the dataset significantly better than the nested
+    /// `model_a`, e.g., because it has an extra component. Returns the statistic
+    /// `2 (llk_b - llk_a)` and its p-value under a chi-squared distribution with
+    /// `n_parameters_b - n_parameters_a` degrees of freedom. Note that the chi-squared
+    /// approximation is only heuristic when the extra parameters lie on the boundary of
+    /// the parameter space, as is the case for mixture weights.
+    pub fn log_likelihood_ratio_test(
+        model_a: &PPCAMix,
+        model_b: &PPCAMix,
+        dataset: &Dataset,
+    ) -> (f64, f64) {
+        assert!(
+            model_b.n_parameters() >= model_a.n_parameters(),
+            "model_b must have at least as many parameters as model_a"
+        );
+        let statistic = 2.0 * (model_b.llk(dataset) - model_a.llk(dataset));
+        let dof = model_b.n_parameters() - model_a.n_parameters();
+        let p_value = if dof == 0 {
+            // The chi-squared distribution collapses to zero.
+            if statistic > 0.0 {
+                0.0
+            } else {
+                1.0
+            }
+        } else {
+            chi_squared_sf(statistic, dof as f64)
+        };
+
+        (statistic, p_value)
+    }
+
+    /// A soft version of `PPCAModel::degrees_of_freedom_observation`, averaging the
+    /// degrees of freedom of each component over the posterior cluster probabilities.
+    pub fn degrees_of_freedom_approx(&self, sample: &MaskedSample) -> f64 {
+        let posterior = log_softmax(self.llks_one(sample) + &self.log_weights).map(f64::exp);
+        self.models
+            .iter()
+            .zip(&posterior)
+            .map(|(model, &weight)| weight * model.degrees_of_freedom_observation(sample) as f64)
+            .sum()
+    }
+
     pub fn infer_cluster(&self, dataset: &Dataset) -> DMatrix<f64> {
         let rows: Vec<_> = dataset
             .data
             .par_iter()
-            .map(|sample| robust_log_softmax(self.llks_one(sample) + &self.log_weights).transpose())
+            .map(|sample| log_softmax(self.llks_one(sample) + &self.log_weights).transpose())
             .collect();
 
-        DMatrix::from_rows(&*rows)
-    }
-
-    pub(crate) fn infer_one(&self, sample: &MaskedSample) -> InferredMaskedMix {
-        InferredMaskedMix {
-            log_posterior: robust_log_softmax(self.llks_one(sample) + &self.log_weights),
-            inferred: self
-                .models
-                .iter()
-                .map(|model| model.infer_one(sample))
-                .collect::<Vec<_>>(),
+        if rows.is_empty() {
+            DMatrix::zeros(0, self.n_components())
+        } else {
+            DMatrix::from_rows(&*rows)
         }
     }
 
-    pub fn infer(&self, dataset: &Dataset) -> Vec<InferredMaskedMix> {
-        dataset
-            .data
-            .par_iter()
-            .map(|sample| self.infer_one(sample))
+    /// Decodes `n_steps` evenly spaced points between `from` and `to`, endpoints
+    /// included, as in `PPCAModel::interpolate`. Each endpoint is encoded and decoded by
+    /// the component with the highest posterior for it; since decoding is affine, this
+    /// is the latent-space interpolation when both endpoints share the same component.
+    pub fn interpolate(&self, from: &MaskedSample, to: &MaskedSample, n_steps: usize) -> Dataset {
+        assert!(n_steps >= 2, "need at least the two endpoints");
+        let decode = |sample: &MaskedSample| {
+            let model_idx = (self.llks_one(sample) + &self.log_weights).imax();
+            self.models[model_idx].smooth_one(sample).data_vector()
+        };
+        let from_output = decode(from);
+        let to_output = decode(to);
+
+        (0..n_steps)
+            .map(|step| {
+                let t = step as f64 / (n_steps - 1) as f64;
+                MaskedSample::unmasked((1.0 - t) * &from_output + t * &to_output)
+            })
             .collect()
     }
 
-    pub fn smooth(&self, dataset: &Dataset) -> Dataset {
-        let smooths = self
+    /// Projects each sample to 2D with `PPCAModel::project_to_2d` under its most
+    /// probable component, returned alongside. All components need at least two hidden
+    /// dimensions.
+    pub fn project_to_2d_with_labels(&self, dataset: &Dataset) -> (DMatrix<f64>, Vec<usize>) {
+        let labels = self.hard_cluster_assignments(dataset);
+        let columns = self
             .models
             .iter()
-            .map(|model| model.smooth(dataset))
+            .map(|model| model.largest_loading_columns(2))
             .collect::<Vec<_>>();
-        let clusters = self.infer_cluster(dataset);
+        let rows = dataset
+            .data
+            .par_iter()
+            .zip(&labels)
+            .map(|(sample, &label)| {
+                self.models[label]
+                    .infer_one(sample)
+                    .state()
+                    .select_rows(&columns[label])
+                    .transpose()
+            })
+            .collect::<Vec<_>>();
+        let projected = if rows.is_empty() {
+            DMatrix::zeros(0, 2)
+        } else {
+            DMatrix::from_rows(&rows)
+        };
 
-        (0..dataset.len())
-            .into_par_iter()
-            .map(|i| {
-                let posterior = clusters.row(i).map(f64::exp);
-                let smoothed = posterior
+        (projected, labels)
+    }
+
+    /// The effective number of samples of the dataset each component explains,
+    /// `Σ_i w_i p(k | x_i)`, where `w_i` are the sample weights. These sum to the total
+    /// weight of the dataset.
+    pub fn component_sample_sizes(&self, dataset: &Dataset) -> DVector<f64> {
+        self.compute_responsibilities(dataset)
+            .n_effective_samples_per_component()
+    }
+
+    /// The average log-likelihood under each component of the samples it is responsible
+    /// for, `Σ_i p(k|x_i) log p(x_i|model_k) / Σ_i p(k|x_i)`, with the samples weighted by
+    /// their dataset weights. Components with very negative values fail to explain the
+    /// data assigned to them. Components responsible for no sample get NaN.
+    pub fn average_llk_per_component(&self, dataset: &Dataset) -> DVector<f64> {
+        let responsibilities = self.compute_responsibilities(dataset);
+        let rows: Vec<_> = dataset
+            .data
+            .par_iter()
+            .map(|sample| self.llks_one(sample).transpose())
+            .collect();
+        let component_llks = if rows.is_empty() {
+            DMatrix::zeros(0, self.n_components())
+        } else {
+            DMatrix::from_rows(&*rows)
+        };
+        let weights = DVector::from(dataset.weights.clone());
+
+        responsibilities
+            .matrix()
+            .component_mul(&component_llks)
+            .tr_mul(&weights)
+            .component_div(&responsibilities.n_effective_samples_per_component())
+    }
+
+    /// Computes the cluster posteriors of each sample once, for reuse in the several
+    /// quantities derived from them, see `Responsibilities`.
+    pub fn compute_responsibilities(&self, dataset: &Dataset) -> Responsibilities {
+        Responsibilities {
+            posteriors: self.infer_cluster(dataset).map(f64::exp),
+            dataset: dataset.clone(),
+        }
+    }
+
+    /// The cluster posteriors of each sample, keeping only the `(component, responsibility)`
+    /// pairs whose responsibility exceeds `threshold`, and always the most probable
+    /// component, so that no sample is left without any. The kept responsibilities are
+    /// not renormalized.
+    pub fn sparse_responsibilities(
+        &self,
+        dataset: &Dataset,
+        threshold: f64,
+    ) -> Vec<Vec<(usize, f64)>> {
+        dataset
+            .data
+            .par_iter()
+            .map(|sample| {
+                let posteriors =
+                    log_softmax(self.llks_one(sample) + &self.log_weights).map(f64::exp);
+                let most_probable = posteriors.imax();
+                posteriors
                     .iter()
-                    .zip(&*smooths[i].data)
-                    .map(|(&pi, smooth_i)| pi * smooth_i.masked_vector())
-                    .sum();
-                MaskedSample::unmasked(smoothed)
+                    .copied()
+                    .enumerate()
+                    .filter(|&(component, posterior)| {
+                        posterior > threshold || component == most_probable
+                    })
+                    .collect()
             })
             .collect()
     }
 
-    pub fn extrapolate(&self, dataset: &Dataset) -> Dataset {
-        let exrapolated = self
+    /// Like `PPCAMix::iterate`, but each component is only updated with the samples in
+    /// `PPCAMix::sparse_responsibilities`, ignoring the responsibilities below
+    /// `threshold`. With a threshold of zero, this is `PPCAMix::iterate`, and with a
+    /// threshold of one, hard EM. As in `PPCAMix::iterate`, the samples are weighted by
+    /// their responsibilities alone and the dataset weights are ignored. A component with
+    /// no significant samples is kept as is and gets zero weight.
+    pub fn iterate_sparse(&self, dataset: &Dataset, threshold: f64) -> PPCAMix {
+        let responsibilities = self.sparse_responsibilities(dataset, threshold);
+        let mut component_datasets = vec![vec![]; self.n_components()];
+        for (sample, responsibilities) in dataset.data.iter().zip(&responsibilities) {
+            for &(component, responsibility) in responsibilities {
+                component_datasets[component].push((sample.clone(), responsibility));
+            }
+        }
+
+        let (iterated_models, log_weights): (Vec<_>, Vec<f64>) = self
             .models
             .iter()
-            .map(|model| model.extrapolate(dataset))
-            .collect::<Vec<_>>();
-        let clusters = self.infer_cluster(dataset);
+            .zip(component_datasets)
+            .map(|(model, component_dataset)| {
+                if component_dataset.is_empty() {
+                    return (model.clone(), f64::NEG_INFINITY);
+                }
 
-        (0..dataset.len())
-            .into_par_iter()
-            .map(|i| {
-                let posterior = clusters.row(i).map(f64::exp);
-                let smoothed = posterior
-                    .iter()
-                    .zip(&*exrapolated[i].data)
-                    .map(|(&pi, extrap_i)| pi * extrap_i.masked_vector())
-                    .sum();
-                MaskedSample::unmasked(smoothed)
+                let dataset = component_dataset.into_iter().collect::<Dataset>();
+                let total_weight = dataset.weights.iter().sum::<f64>();
+                (model.iterate(&dataset), total_weight.ln())
             })
-            .collect()
-    }
+            .unzip();
+
+        PPCAMix {
+            output_size: self.output_size,
+            models: iterated_models,
+            log_weights: log_softmax(log_weights.into()),
+        }
+    }
+
+    /// The most probable component of each sample.
+    pub fn hard_cluster_assignments(&self, dataset: &Dataset) -> Vec<usize> {
+        dataset
+            .data
+            .par_iter()
+            .map(|sample| (self.llks_one(sample) + &self.log_weights).imax())
+            .collect()
+    }
+
+    /// Draws a fully observed sample from the conditional distribution given the observed
+    /// values of `sample`: a component is drawn from its posterior `p(k | x_observed)`
+    /// and the missing values from `p(x_missing | x_observed, k)`, as in
+    /// `PPCAModel::sample_conditional`. The randomness is derived deterministically from
+    /// `seed`.
+    pub fn sample_conditional(&self, sample: &MaskedSample, seed: u64) -> MaskedSample {
+        self.sample_conditional_with_rng(sample, &mut StdRng::seed_from_u64(seed))
+    }
+
+    /// Draws `n_samples` independent conditional samples, see
+    /// `PPCAMix::sample_conditional`.
+    pub fn sample_conditional_n(
+        &self,
+        sample: &MaskedSample,
+        n_samples: usize,
+        seed: u64,
+    ) -> Vec<MaskedSample> {
+        let mut rng = StdRng::seed_from_u64(seed);
+        (0..n_samples)
+            .map(|_| self.sample_conditional_with_rng(sample, &mut rng))
+            .collect()
+    }
+
+    fn sample_conditional_with_rng<R: Rng + ?Sized>(
+        &self,
+        sample: &MaskedSample,
+        rng: &mut R,
+    ) -> MaskedSample {
+        let posterior = log_softmax(self.llks_one(sample) + &self.log_weights).map(f64::exp);
+        let model_idx = WeightedIndex::new(posterior.iter())
+            .expect("can create WeigtedIndex from distribution")
+            .sample(rng);
+        self.models[model_idx].sample_conditional_with_rng(sample, rng)
+    }
+
+    /// Draws `n_draws` outputs by ancestral sampling under the posterior given `sample`:
+    /// a component from `p(k | x_observed)`, a hidden state from `p(z | x_observed, k)`
+    /// and then the whole output, observed dimensions included, with its noise. Unlike
+    /// `PPCAMix::smooth`, which averages over the components, this keeps the
+    /// multimodality of the posterior; the draws average to the smoothed value. The
+    /// randomness is derived deterministically from `seed`.
+    pub fn posterior_sample(
+        &self,
+        sample: &MaskedSample,
+        n_draws: usize,
+        seed: u64,
+    ) -> Vec<DVector<f64>> {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let posterior = log_softmax(self.llks_one(sample) + &self.log_weights).map(f64::exp);
+        let index = WeightedIndex::new(posterior.iter())
+            .expect("can create WeigtedIndex from distribution");
+
+        (0..n_draws)
+            .map(|_| {
+                let model_idx = index.sample(&mut rng);
+                self.models[model_idx].sample_posterior_output_with_rng(sample, &mut rng)
+            })
+            .collect()
+    }
+
+    pub(crate) fn infer_one(&self, sample: &MaskedSample) -> InferredMaskedMix {
+        InferredMaskedMix {
+            log_posterior: log_softmax(self.llks_one(sample) + &self.log_weights),
+            inferred: self
+                .models
+                .iter()
+                .map(|model| model.infer_one(sample))
+                .collect::<Vec<_>>(),
+        }
+    }
+
+    /// Predicts the values of `target_dims` given the values of `observed_dims`, as in
+    /// `PPCAModel::predict_from_partial`. The mean is averaged over the components with
+    /// their posteriors `p(k | x_observed)`, and the standard deviation accounts for the
+    /// uncertainty on the component, through the law of total variance.
+    pub fn predict_from_partial(
+        &self,
+        observed_dims: &[usize],
+        observed_values: &DVector<f64>,
+        target_dims: &[usize],
+    ) -> (DVector<f64>, DVector<f64>) {
+        let sample = MaskedSample::from_partial(self.output_size, observed_dims, observed_values);
+        let inferred = self.infer_one(&sample);
+        let mean = inferred.extrapolated(self, &sample);
+        let variance = inferred.extrapolated_covariance_diagonal(self, &sample);
+
+        (
+            mean.select_rows(target_dims),
+            variance.select_rows(target_dims).map(f64::sqrt),
+        )
+    }
+
+    pub fn infer(&self, dataset: &Dataset) -> Vec<InferredMaskedMix> {
+        dataset
+            .data
+            .par_iter()
+            .map(|sample| self.infer_one(sample))
+            .collect()
+    }
+
+    /// Like `PPCAMix::infer`, but returns an error instead of panicking when the dataset
+    /// is malformed or its samples do not have the output size of this mixture.
+    pub fn try_infer(&self, dataset: &Dataset) -> Result<Vec<InferredMaskedMix>, DatasetError> {
+        self.check_dataset(dataset)?;
+        Ok(self.infer(dataset))
+    }
+
+    /// Like `PPCAMix::smooth`, but returns an error instead of panicking when the dataset
+    /// is malformed or its samples do not have the output size of this mixture.
+    pub fn try_smooth(&self, dataset: &Dataset) -> Result<Dataset, DatasetError> {
+        self.check_dataset(dataset)?;
+        Ok(self.smooth(dataset))
+    }
+
+    /// Like `PPCAMix::extrapolate`, but returns an error instead of panicking when the
+    /// dataset is malformed or its samples do not have the output size of this mixture.
+    pub fn try_extrapolate(&self, dataset: &Dataset) -> Result<Dataset, DatasetError> {
+        self.check_dataset(dataset)?;
+        Ok(self.extrapolate(dataset))
+    }
+
+    pub fn smooth(&self, dataset: &Dataset) -> Dataset {
+        let smooths = self
+            .models
+            .iter()
+            .map(|model| model.smooth(dataset))
+            .collect::<Vec<_>>();
+        let clusters = self.infer_cluster(dataset);
+
+        (0..dataset.len())
+            .into_par_iter()
+            .map(|i| {
+                let posterior = clusters.row(i).map(f64::exp);
+                let smoothed = posterior
+                    .iter()
+                    .zip(&smooths)
+                    .map(|(&pi, smooth)| pi * smooth.data[i].masked_vector())
+                    .sum();
+                MaskedSample::unmasked(smoothed)
+            })
+            .collect()
+    }
+
+    pub fn extrapolate(&self, dataset: &Dataset) -> Dataset {
+        let exrapolated = self
+            .models
+            .iter()
+            .map(|model| model.extrapolate(dataset))
+            .collect::<Vec<_>>();
+        let clusters = self.infer_cluster(dataset);
+
+        (0..dataset.len())
+            .into_par_iter()
+            .map(|i| {
+                let posterior = clusters.row(i).map(f64::exp);
+                let smoothed = posterior
+                    .iter()
+                    .zip(&exrapolated)
+                    .map(|(&pi, extrap)| pi * extrap.data[i].masked_vector())
+                    .sum();
+                MaskedSample::unmasked(smoothed)
+            })
+            .collect()
+    }
+
+    /// Fills in the missing values of each sample with their posterior mean under this
+    /// mixture. Unlike `PPCAMix::smooth`, and unlike `PPCAMix::extrapolate` up to
+    /// rounding, observed values are kept exactly as they are. The weights are kept too.
+    pub fn impute_missing(&self, dataset: &Dataset) -> Dataset {
+        dataset
+            .data
+            .par_iter()
+            .zip(&dataset.weights)
+            .map(|(sample, &weight)| {
+                let smoothed = self.infer_one(sample).smoothed(self);
+                (
+                    MaskedSample::unmasked(sample.mask().choose(&sample.data_vector(), &smoothed)),
+                    weight,
+                )
+            })
+            .collect()
+    }
 
     pub fn iterate(&self, dataset: &Dataset) -> PPCAMix {
+        self.iterate_with_config(dataset, &PPCAFitConfig::default())
+    }
+
+    /// Performs one EM iteration, updating each component with
+    /// `PPCAModel::iterate_with_config`.
+    pub fn iterate_with_config(&self, dataset: &Dataset, config: &PPCAFitConfig) -> PPCAMix {
+        self.iterate_components(dataset, &vec![1; self.n_components()], config)
+    }
+
+    /// Like `PPCAMix::iterate`, but component `k` makes `n_iters_per_component[k]`
+    /// iterations with the cluster posteriors held fixed before the mixture weights are
+    /// updated. This lets components with bigger states, which converge slower, catch
+    /// up. With one iteration per component, this is the same as `PPCAMix::iterate`.
+    pub fn component_wise_iterate(
+        &self,
+        dataset: &Dataset,
+        n_iters_per_component: &[usize],
+    ) -> PPCAMix {
+        assert_eq!(
+            n_iters_per_component.len(),
+            self.n_components(),
+            "need one number of iterations per component"
+        );
+        self.iterate_components(dataset, n_iters_per_component, &PPCAFitConfig::default())
+    }
+
+    fn iterate_components(
+        &self,
+        dataset: &Dataset,
+        n_iters_per_component: &[usize],
+        config: &PPCAFitConfig,
+    ) -> PPCAMix {
         // This is already parallelized internally; no need to further parallelize.
         let llks = self
             .models
@@ -213,15 +1149,16 @@ impl PPCAMix {
             .into_par_iter()
             .map(|idx| {
                 let llk: DVector<f64> = llks.iter().map(|llk| llk[idx]).collect::<Vec<_>>().into();
-                robust_log_softmax(llk + &self.log_weights)
+                log_softmax(llk + &self.log_weights)
             })
             .collect::<Vec<_>>();
 
         let (iterated_models, log_weights): (Vec<_>, Vec<f64>) = self
             .models
             .iter()
+            .zip(n_iters_per_component)
             .enumerate()
-            .map(|(i, model)| {
+            .map(|(i, (model, &n_iters))| {
                 // Log-posteriors for this particulat model.
                 let log_posteriors: Vec<_> = log_posteriors.par_iter().map(|lp| lp[i]).collect();
                 // Let the NaN silently propagate... everything will blow up before this
@@ -242,15 +1179,225 @@ impl PPCAMix {
                     unnorm_posteriors.iter().copied().sum::<f64>().ln() + max_posterior;
                 let dataset = dataset.with_weights(unnorm_posteriors);
 
-                (model.iterate(&dataset), logsum_posteriors)
+                let iterated = (0..n_iters).fold(model.clone(), |model, _| {
+                    model.iterate_with_config(&dataset, config)
+                });
+
+                (iterated, logsum_posteriors)
             })
             .unzip();
 
         PPCAMix {
             output_size: self.output_size,
             models: iterated_models,
-            log_weights: robust_log_softmax(log_weights.into()),
+            log_weights: log_softmax(log_weights.into()),
+        }
+    }
+
+    fn reordered(&self, order: &[usize]) -> PPCAMix {
+        PPCAMix {
+            output_size: self.output_size,
+            models: order.iter().map(|&idx| self.models[idx].clone()).collect(),
+            log_weights: order
+                .iter()
+                .map(|&idx| self.log_weights[idx])
+                .collect::<Vec<_>>()
+                .into(),
+        }
+    }
+
+    /// Sorts the components by the lexicographic order of their means (first by `μ[0]`,
+    /// then by `μ[1]`, etc.). This gives a canonical labeling of the components, making
+    /// mixtures from different runs comparable.
+    pub fn reorder_components_by_mean(&self) -> PPCAMix {
+        let mut order = (0..self.n_components()).collect::<Vec<_>>();
+        order.sort_by(|&a, &b| {
+            self.models[a]
+                .output_mean()
+                .iter()
+                .zip(self.models[b].output_mean().iter())
+                .map(|(a, b)| a.total_cmp(b))
+                .find(|ordering| ordering.is_ne())
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+        self.reordered(&order)
+    }
+
+    /// Sorts the components from the largest to the smallest weight.
+    pub fn reorder_components_by_weight(&self) -> PPCAMix {
+        let mut order = (0..self.n_components()).collect::<Vec<_>>();
+        order.sort_by(|&a, &b| self.log_weights[b].total_cmp(&self.log_weights[a]));
+        self.reordered(&order)
+    }
+
+    /// Fits a mixture with the same state sizes as this one from `n_restarts` independent
+    /// random initializations, running in parallel, and returns the one with the highest
+    /// final log-likelihood. Each run iterates EM until the log-likelihood improves by
+    /// less than `tol` or for at most `max_iter` iterations. The initializations are
+    /// derived deterministically from `seed`.
+    pub fn fit_with_restarts(
+        &self,
+        dataset: &Dataset,
+        n_restarts: usize,
+        max_iter: usize,
+        tol: f64,
+        seed: u64,
+    ) -> PPCAMix {
+        assert!(n_restarts > 0, "need at least one restart");
+        let mut rng = StdRng::seed_from_u64(seed);
+        let restart_seeds = (0..n_restarts).map(|_| rng.gen()).collect::<Vec<u64>>();
+
+        restart_seeds
+            .into_par_iter()
+            .map(|restart_seed| {
+                let mut rng = StdRng::seed_from_u64(restart_seed);
+                let mut mix = PPCAMix::new(
+                    self.models
+                        .iter()
+                        .map(|model| {
+                            PPCAModel::init_with_rng(model.state_size(), dataset, &mut rng)
+                        })
+                        .collect(),
+                    vec![0.0; self.n_components()].into(),
+                );
+                let mut llk = mix.llk(dataset);
+
+                for _ in 0..max_iter {
+                    mix = mix.iterate(dataset);
+                    let new_llk = mix.llk(dataset);
+                    let converged = (new_llk - llk).abs() < tol;
+                    llk = new_llk;
+
+                    if converged {
+                        break;
+                    }
+                }
+
+                (mix, llk)
+            })
+            .filter(|(_, llk)| llk.is_finite())
+            .max_by(|(_, llk), (_, other_llk)| llk.total_cmp(other_llk))
+            .expect("at least one restart has finite log-likelihood")
+            .0
+    }
+
+    /// Selects the number of components by `n_folds`-fold cross-validation: for each
+    /// candidate, fits a mixture of components with `state_size` hidden dimensions with
+    /// `PPCAMix::fit_with_restarts` on each training fold, and returns the held-out
+    /// log-likelihood per sample, averaged over all folds. The best candidate is the one
+    /// with the largest value.
+    pub fn cross_validate_n_components(
+        dataset: &Dataset,
+        candidate_counts: &[usize],
+        state_size: usize,
+        n_folds: usize,
+        n_restarts: usize,
+        seed: u64,
+    ) -> Vec<(usize, f64)> {
+        let folds = dataset.folds(n_folds);
+        let mut rng = StdRng::seed_from_u64(seed);
+        let runs = candidate_counts
+            .iter()
+            .flat_map(|&n_components| (0..n_folds).map(move |fold| (n_components, fold)))
+            .map(|(n_components, fold)| (n_components, fold, rng.gen()))
+            .collect::<Vec<(usize, usize, u64)>>();
+
+        let held_out_llks = runs
+            .into_par_iter()
+            .map(|(n_components, fold, run_seed)| {
+                let (training, validation) = &folds[fold];
+                PPCAMix::init(n_components, state_size, training)
+                    .fit_with_restarts(
+                        training,
+                        n_restarts,
+                        CROSS_VALIDATION_MAX_ITER,
+                        CROSS_VALIDATION_TOL,
+                        run_seed,
+                    )
+                    .llk(validation)
+            })
+            .collect::<Vec<_>>();
+
+        let total_weight = dataset.weights.iter().sum::<f64>();
+        candidate_counts
+            .iter()
+            .zip(held_out_llks.chunks(n_folds))
+            .map(|(&n_components, llks)| (n_components, llks.iter().sum::<f64>() / total_weight))
+            .collect()
+    }
+
+    /// Fits this mixture to a dataset with EM, starting from this mixture, as configured
+    /// by `config`. Returns the fitted mixture and the log-likelihood before the first
+    /// iteration and after each one.
+    pub fn fit(&self, dataset: &Dataset, config: &PPCAFitConfig) -> (PPCAMix, Vec<f64>) {
+        config.run(
+            self.clone(),
+            |mix| mix.llk(dataset),
+            |mix| mix.iterate_with_config(dataset, config),
+        )
+    }
+
+    /// Runs `n_iter` EM iterations from this mixture and keeps every intermediate
+    /// mixture, starting with this one, together with its log-likelihood, e.g., to
+    /// visualize components collapsing or merging. This stores `n_iter + 1` mixtures.
+    pub fn simulate_em_trajectory(&self, dataset: &Dataset, n_iter: usize) -> Vec<(PPCAMix, f64)> {
+        let mut trajectory = Vec::with_capacity(n_iter + 1);
+        let mut mix = self.clone();
+
+        for _ in 0..n_iter {
+            let iterated = mix.iterate(dataset);
+            let llk = mix.llk(dataset);
+            trajectory.push((mix, llk));
+            mix = iterated;
         }
+
+        let llk = mix.llk(dataset);
+        trajectory.push((mix, llk));
+
+        trajectory
+    }
+
+    /// Runs `PPCAMix::fit` in a background thread, sending an `IterationReport` through
+    /// the returned channel after each iteration, e.g., to display progress. Joining
+    /// the returned handle gives the fitted mixture.
+    pub fn fit_monitor(
+        &self,
+        dataset: &Dataset,
+        config: &PPCAFitConfig,
+    ) -> (Receiver<IterationReport>, JoinHandle<PPCAMix>) {
+        let (sender, receiver) = mpsc::channel();
+        let init = self.clone();
+        let dataset = dataset.clone();
+        let config = config.clone();
+
+        let handle = thread::spawn(move || {
+            let (mix, _) = config.run_with_reports(
+                init,
+                |mix| mix.llk(&dataset),
+                |mix| mix.iterate_with_config(&dataset, &config),
+                // The receiver may be gone: the fit goes on regardless.
+                |report| sender.send(report).unwrap_or(()),
+            );
+            mix
+        });
+
+        (receiver, handle)
+    }
+
+    /// Iterates EM until the relative improvement of the log-likelihood,
+    /// `(llk_new - llk_old) / |llk_old|`, falls below `tol`, or for at most `max_iter`
+    /// iterations. Returns the fitted mixture and the log-likelihood before the first
+    /// iteration and after each one, i.e., `n_iterations + 1` values.
+    pub fn iterate_until_convergence(
+        &self,
+        dataset: &Dataset,
+        tol: f64,
+        max_iter: usize,
+    ) -> (PPCAMix, Vec<f64>) {
+        self.fit(
+            dataset,
+            &PPCAFitConfig::default().tol(tol).max_iter(max_iter),
+        )
     }
 
     pub fn to_canonical(&self) -> PPCAMix {
@@ -377,3 +1524,1095 @@ impl InferredMaskedMix {
             .sum()
     }
 }
+
+/// The cluster posteriors `p(k | x_i)` of each sample of a dataset under a mixture, as
+/// computed by `PPCAMix::compute_responsibilities`.
+#[derive(Debug, Clone)]
+pub struct Responsibilities {
+    /// One row per sample and one column per component.
+    posteriors: DMatrix<f64>,
+    dataset: Dataset,
+}
+
+impl Responsibilities {
+    /// The matrix of responsibilities, with one row per sample and one column per
+    /// component.
+    pub fn matrix(&self) -> &DMatrix<f64> {
+        &self.posteriors
+    }
+
+    /// The most probable component of each sample.
+    pub fn hard_assignments(&self) -> Vec<usize> {
+        self.posteriors
+            .row_iter()
+            .map(|row| row.transpose().imax())
+            .collect()
+    }
+
+    /// The entropy of the cluster posterior of each sample, in nats. This is zero for
+    /// certain assignments and `log K` for completely uncertain ones.
+    pub fn entropy(&self) -> DVector<f64> {
+        self.posteriors
+            .row_iter()
+            .map(|row| {
+                -row.iter()
+                    .filter(|&&posterior| posterior > 0.0)
+                    .map(|&posterior| posterior * posterior.ln())
+                    .sum::<f64>()
+            })
+            .collect::<Vec<_>>()
+            .into()
+    }
+
+    /// The sum of the responsibilities of each component, weighted by the sample
+    /// weights. This is the effective number of samples each component explains.
+    pub fn n_effective_samples_per_component(&self) -> DVector<f64> {
+        self.posteriors
+            .tr_mul(&DVector::from(self.dataset.weights.clone()))
+    }
+
+    /// The dataset with each sample weighted by its responsibility for component `k`,
+    /// on top of its original weight, for fitting component `k` on its own. Note that
+    /// `PPCAMix::iterate` weights the samples by the responsibilities alone, ignoring
+    /// the original weights.
+    pub fn weighted_dataset(&self, k: usize) -> Dataset {
+        assert!(k < self.posteriors.ncols(), "component index out of range");
+        self.dataset.with_weights(
+            self.dataset
+                .weights
+                .iter()
+                .zip(self.posteriors.column(k).iter())
+                .map(|(&weight, &posterior)| weight * posterior)
+                .collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bit_vec::BitVec;
+    use nalgebra::{dmatrix, dvector};
+
+    use crate::utils::Mask;
+
+    use super::*;
+
+    fn toy_mix() -> PPCAMix {
+        PPCAMix::new(
+            vec![
+                PPCAModel::new(
+                    0.1,
+                    dmatrix![
+                        1.0, 0.0, 0.0;
+                        0.0, 0.0, 1.0;
+                    ]
+                    .transpose(),
+                    dvector![1.0, 1.0, 1.0],
+                ),
+                PPCAModel::new(
+                    0.1,
+                    dmatrix![1.0, 1.0, 0.0;].transpose(),
+                    dvector![0.0, 1.0, 0.0],
+                ),
+            ],
+            dvector![0.33333f64.ln(), 0.66667f64.ln()],
+        )
+    }
+
+    #[test]
+    fn test_n_parameters() {
+        let mix = toy_mix();
+        let n_models = mix.n_components();
+        let model_parameters = mix
+            .models()
+            .iter()
+            .map(PPCAModel::n_parameters)
+            .sum::<usize>();
+
+        assert_eq!(mix.models()[0].n_parameters(), 3 * 2 + 3 + 1 - 1);
+        assert_eq!(mix.models()[1].n_parameters(), 3 + 3 + 1);
+        assert_eq!(mix.n_parameters(), model_parameters + n_models - 1);
+    }
+
+    #[test]
+    fn test_n_free_parameters() {
+        // A loading column, a mean and a noise in two dimensions.
+        let single = PPCAMix::new(
+            vec![PPCAModel::new(0.1, dmatrix![1.0; 2.0], dvector![0.0, 0.0])],
+            dvector![0.0],
+        );
+        assert_eq!(single.n_free_parameters(), 2 + 2 + 1);
+
+        let mix = toy_mix();
+        let expected = mix
+            .state_sizes()
+            .iter()
+            .map(|&k| 3 * k - k * (k - 1) / 2 + 3 + 1)
+            .sum::<usize>()
+            + 1;
+        assert_eq!(mix.n_free_parameters(), expected);
+    }
+
+    #[test]
+    fn test_param_vector_round_trip() {
+        let mix = toy_mix();
+        let params = mix.param_vector();
+        assert_eq!(params.len(), (3 * 2 + 3 + 1) + (3 + 3 + 1) + 1);
+
+        let reconstructed = PPCAMix::from_param_vector(3, &mix.state_sizes(), &params);
+        approx::assert_relative_eq!(reconstructed.param_vector(), params);
+        approx::assert_relative_eq!(*reconstructed.log_weights(), *mix.log_weights());
+
+        // Any free log-weights give a normalized mixture.
+        let mut shifted = params.clone();
+        let n_params = shifted.len();
+        shifted[n_params - 1] += 3.0;
+        let reconstructed = PPCAMix::from_param_vector(3, &mix.state_sizes(), &shifted);
+        approx::assert_relative_eq!(
+            reconstructed.log_weights().map(f64::exp).sum(),
+            1.0,
+            epsilon = 1e-12
+        );
+    }
+
+    #[test]
+    fn test_sparse_responsibilities() {
+        let mix = toy_mix();
+        let dataset = mix.sample(100, 0.2);
+        let dense = mix.infer_cluster(&dataset).map(f64::exp);
+
+        let sparse = mix.sparse_responsibilities(&dataset, 0.1);
+        assert_eq!(sparse.len(), 100);
+        for (responsibilities, dense_row) in sparse.iter().zip(dense.row_iter()) {
+            for &(component, responsibility) in responsibilities {
+                assert!(responsibility > 0.1);
+                approx::assert_relative_eq!(responsibility, dense_row[component]);
+            }
+            assert_eq!(
+                responsibilities.len(),
+                dense_row.iter().filter(|&&p| p > 0.1).count()
+            );
+        }
+
+        // Without pruning, the sparse update is the usual EM update, also on a weighted
+        // dataset, whose weights both ignore.
+        let weighted = dataset.with_weights((0..100).map(|idx| 0.5 + (idx % 3) as f64).collect());
+        for dataset in [&dataset, &weighted] {
+            let iterated = mix.iterate(dataset);
+            let iterated_sparse = mix.iterate_sparse(dataset, 0.0);
+            approx::assert_relative_eq!(
+                iterated_sparse.param_vector(),
+                iterated.param_vector(),
+                epsilon = 1e-8,
+                max_relative = 1e-6
+            );
+        }
+
+        // A threshold pruning every posterior still keeps the most probable component of
+        // each sample, i.e., this is hard EM.
+        let hard = mix.sparse_responsibilities(&dataset, 1.0);
+        let assignments = mix.hard_cluster_assignments(&dataset);
+        for (responsibilities, &assignment) in hard.iter().zip(&assignments) {
+            assert_eq!(responsibilities.len(), 1);
+            assert_eq!(responsibilities[0].0, assignment);
+        }
+        let iterated_hard = mix.iterate_sparse(&dataset, 1.0);
+        assert!(iterated_hard.log_weights().iter().all(|w| !w.is_nan()));
+        for model in iterated_hard.models() {
+            assert!(model.param_vector().iter().all(|p| p.is_finite()));
+        }
+        assert!(iterated_hard.llk(&dataset).is_finite());
+    }
+
+    #[test]
+    fn test_try_methods_reject_malformed_datasets() {
+        let mix = toy_mix();
+        let dataset = mix.sample_with_rng(20, 0.2, &mut StdRng::seed_from_u64(0));
+        approx::assert_relative_eq!(
+            mix.try_llks(&dataset).expect("dataset is valid"),
+            mix.llks(&dataset)
+        );
+        approx::assert_relative_eq!(
+            mix.try_llk(&dataset).expect("dataset is valid"),
+            mix.llk(&dataset)
+        );
+
+        let ragged = Dataset::new(vec![
+            MaskedSample::unmasked(dvector![1.0, 2.0, 3.0]),
+            MaskedSample::unmasked(dvector![1.0, 2.0]),
+        ]);
+        let wrong_size = Dataset::new(vec![MaskedSample::unmasked(dvector![1.0, 2.0])]);
+        assert_eq!(
+            mix.try_llks(&wrong_size),
+            Err(DatasetError::DimensionMismatch {
+                sample: 0,
+                expected: 3,
+                found: 2
+            })
+        );
+        for dataset in [&ragged, &wrong_size] {
+            assert!(mix.try_llks(dataset).is_err());
+            assert!(mix.try_llk(dataset).is_err());
+            assert!(mix.try_infer(dataset).is_err());
+            assert!(mix.try_smooth(dataset).is_err());
+            assert!(mix.try_extrapolate(dataset).is_err());
+        }
+    }
+
+    #[test]
+    fn test_conditional_llk() {
+        let mix = toy_mix();
+        let dataset = mix.sample(10, 0.5);
+        let llks = mix.conditional_llk(&dataset);
+
+        for (sample, &llk) in dataset.data.iter().zip(&llks) {
+            let expected = mix
+                .models()
+                .iter()
+                .zip(mix.log_weights())
+                .map(|(model, &log_weight)| (model.conditional_llk(sample) + log_weight).exp())
+                .sum::<f64>()
+                .ln();
+            approx::assert_relative_eq!(llk, expected, epsilon = 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_fit_with_restarts() {
+        let mix = toy_mix();
+        let dataset = mix.sample(300, 0.2);
+
+        // The first restart is the same for any number of restarts, so more restarts
+        // can only do better.
+        let single = mix.fit_with_restarts(&dataset, 1, 30, 1e-6, 42);
+        let best = mix.fit_with_restarts(&dataset, 4, 30, 1e-6, 42);
+        assert_eq!(best.state_sizes(), mix.state_sizes());
+        assert!(best.llk(&dataset) >= single.llk(&dataset) - 1e-6 * single.llk(&dataset).abs());
+    }
+
+    #[test]
+    fn test_reorder_components() {
+        let mix = toy_mix();
+
+        let by_mean = mix.reorder_components_by_mean();
+        assert_eq!(by_mean.state_sizes(), vec![1, 2]);
+        approx::assert_relative_eq!(*by_mean.models()[0].output_mean(), dvector![0.0, 1.0, 0.0]);
+        approx::assert_relative_eq!(
+            by_mean.reorder_components_by_mean().param_vector(),
+            by_mean.param_vector()
+        );
+
+        let by_weight = mix.reorder_components_by_weight();
+        assert!(by_weight.log_weights()[0] >= by_weight.log_weights()[1]);
+        assert_eq!(by_weight.state_sizes(), vec![1, 2]);
+        approx::assert_relative_eq!(
+            by_weight.reorder_components_by_weight().param_vector(),
+            by_weight.param_vector()
+        );
+
+        // Reordering does not change the distribution.
+        let dataset = mix.sample(20, 0.2);
+        approx::assert_relative_eq!(by_mean.llks(&dataset), mix.llks(&dataset), epsilon = 1e-12);
+    }
+
+    #[test]
+    fn test_generate_synthetic() {
+        let (mix, dataset) = PPCAMix::generate_synthetic(4, &[2, 1, 1], 3, 50, 0.1, 42);
+        assert_eq!(mix.output_size(), 4);
+        assert_eq!(mix.state_sizes(), vec![2, 1, 1]);
+        assert_eq!(dataset.len(), 50);
+
+        let (same_mix, _) = PPCAMix::generate_synthetic(4, &[2, 1, 1], 3, 50, 0.1, 42);
+        assert_eq!(same_mix.param_vector(), mix.param_vector());
+    }
+
+    #[test]
+    fn test_llk_single() {
+        let mix = toy_mix();
+        let dataset = mix.sample(50, 0.3);
+        let llks = mix.llks(&dataset);
+
+        for (sample, &llk) in dataset.data.iter().zip(&llks) {
+            approx::assert_relative_eq!(mix.llk_single(sample), llk, epsilon = 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_expected_entropy() {
+        let mix = toy_mix();
+        let entropies = mix.component_entropies();
+        assert_eq!(entropies.len(), 2);
+        approx::assert_relative_eq!(entropies[1], mix.models()[1].entropy());
+
+        // Monte Carlo estimate of the entropy of the whole mixture.
+        let dataset = mix.sample(10_000, 0.0);
+        let mixture_entropy = -mix.llk(&dataset) / dataset.len() as f64;
+        assert!(mix.expected_entropy() <= mixture_entropy);
+    }
+
+    #[test]
+    fn test_impute_missing() {
+        let mix = toy_mix();
+        let dataset = mix
+            .sample(50, 0.3)
+            .with_weights((0..50).map(|idx| 1.0 + (idx % 3) as f64).collect());
+        let imputed = mix.impute_missing(&dataset);
+        let extrapolated = mix.extrapolate(&dataset);
+        assert_eq!(imputed.len(), dataset.len());
+        assert_eq!(imputed.weights, dataset.weights);
+
+        for ((sample, imputed), extrapolated) in dataset
+            .data
+            .iter()
+            .zip(&*imputed.data)
+            .zip(&*extrapolated.data)
+        {
+            let imputed = imputed.data_vector();
+            for (dimension, selected) in sample.mask().0.iter().enumerate() {
+                if selected {
+                    assert_eq!(imputed[dimension], sample.data_vector()[dimension]);
+                }
+            }
+            approx::assert_relative_eq!(
+                imputed,
+                extrapolated.data_vector(),
+                epsilon = 1e-9,
+                max_relative = 1e-9
+            );
+        }
+    }
+
+    #[test]
+    fn test_dataset_len() {
+        let dataset = toy_mix().sample(17, 0.0);
+        assert_eq!(dataset.len(), 17);
+        assert!(!dataset.is_empty());
+        assert!(Dataset::new(vec![]).is_empty());
+    }
+
+    #[test]
+    fn test_new_with_state_size() {
+        let dataset = toy_mix().sample(300, 0.2);
+        let mix = PPCAMix::new_with_state_size(&dataset, 2, 1, 42);
+        assert_eq!(mix.n_components(), 2);
+        assert_eq!(mix.state_sizes(), vec![1, 1]);
+        assert!(mix.llk(&dataset).is_finite());
+        approx::assert_relative_eq!(mix.log_weights().map(f64::exp).sum(), 1.0, epsilon = 1e-12);
+    }
+
+    #[test]
+    fn test_fit() {
+        let mix = toy_mix();
+        let dataset = mix.sample(200, 0.2);
+        let init = PPCAMix::init(2, 1, &dataset);
+
+        let (fitted, llks) = init.fit(&dataset, &PPCAFitConfig::default().max_iter(4).tol(0.0));
+        assert_eq!(llks.len(), 5);
+        approx::assert_relative_eq!(llks[4], fitted.llk(&dataset), max_relative = 1e-10);
+    }
+
+    #[test]
+    fn test_iterate_until_convergence() {
+        let mix = toy_mix();
+        let dataset = mix.sample(200, 0.2);
+        let tol = 1e-4;
+        let max_iter = 200;
+        let (_, llks) =
+            PPCAMix::init(2, 1, &dataset).iterate_until_convergence(&dataset, tol, max_iter);
+
+        let relative_improvements = llks
+            .windows(2)
+            .map(|pair| (pair[1] - pair[0]) / pair[0].abs())
+            .collect::<Vec<_>>();
+        let n_iterations = relative_improvements.len();
+        assert!(n_iterations >= 1 && n_iterations <= max_iter);
+        assert!(relative_improvements[..n_iterations - 1]
+            .iter()
+            .all(|&improvement| improvement >= tol));
+        if n_iterations < max_iter {
+            assert!(relative_improvements[n_iterations - 1] < tol);
+        }
+    }
+
+    #[test]
+    fn test_component_wise_iterate() {
+        let mix = toy_mix();
+        let dataset = mix.sample(200, 0.2);
+        let init = PPCAMix::init(2, 1, &dataset);
+
+        let iterated = init.iterate(&dataset);
+        let component_wise = init.component_wise_iterate(&dataset, &[1, 1]);
+        approx::assert_relative_eq!(
+            component_wise.param_vector(),
+            iterated.param_vector(),
+            epsilon = 1e-12,
+            max_relative = 1e-10
+        );
+
+        // Zero iterations keep the component but still update the weights.
+        let frozen = init.component_wise_iterate(&dataset, &[0, 3]);
+        approx::assert_relative_eq!(
+            frozen.models()[0].param_vector(),
+            init.models()[0].param_vector()
+        );
+        approx::assert_relative_eq!(
+            *frozen.log_weights(),
+            *iterated.log_weights(),
+            epsilon = 1e-12
+        );
+    }
+
+    #[test]
+    fn test_log_likelihood_ratio_test() {
+        let model = toy_mix().models()[0].clone();
+        let dataset = model.sample(500, 0.2);
+        let model_a = PPCAMix::new(vec![model.clone()], dvector![0.0]);
+
+        // A spurious component, identical to the first one, brings no improvement.
+        let model_b = PPCAMix::new(vec![model.clone(), model], dvector![0.0, 0.0]);
+        let (statistic, p_value) = PPCAMix::log_likelihood_ratio_test(&model_a, &model_b, &dataset);
+        approx::assert_abs_diff_eq!(statistic, 0.0, epsilon = 1e-6);
+        assert!(p_value > 0.05);
+
+        // The true mixture is much better than a single component on mixture data.
+        let mix = toy_mix();
+        let dataset = mix.sample(500, 0.0);
+        let single = PPCAMix::new(vec![mix.models()[0].clone()], dvector![0.0]);
+        let (statistic, p_value) = PPCAMix::log_likelihood_ratio_test(&single, &mix, &dataset);
+        assert!(statistic > 0.0);
+        assert!(p_value < 0.05);
+    }
+
+    #[test]
+    fn test_hard_cluster_assignments() {
+        let mix = toy_mix();
+        let first = mix.models()[0].sample(50, 0.0);
+        let second = mix.models()[1].sample(50, 0.0);
+        let dataset = first
+            .iter()
+            .chain(second.iter())
+            .cloned()
+            .collect::<Dataset>();
+        let labels = (0..100).map(|idx| idx / 50).collect::<Vec<_>>();
+
+        let assignments = mix.hard_cluster_assignments(&dataset);
+        let metrics = dataset
+            .with_labels(labels)
+            .evaluate_clustering(&assignments);
+        assert!(metrics.purity > 0.9);
+        assert!(metrics.adjusted_rand_index > 0.5);
+    }
+
+    #[test]
+    fn test_sample_conditional() {
+        let mix = toy_mix();
+        let sample = MaskedSample::new(
+            dvector![0.5, 0.0, 0.8],
+            Mask(BitVec::from_iter([true, false, true])),
+        );
+        let imputed = mix.impute_missing(&Dataset::new(vec![sample.clone()])).data[0].data_vector();
+
+        let draws = mix.sample_conditional_n(&sample, 5_000, 42);
+        let mean = draws
+            .iter()
+            .map(MaskedSample::data_vector)
+            .sum::<DVector<f64>>()
+            / draws.len() as f64;
+        assert!(draws.iter().all(|drawn| drawn.data_vector()[0] == 0.5));
+
+        // The mean of the mixture posterior is the mixture of the posterior means.
+        approx::assert_abs_diff_eq!(mean, imputed, epsilon = 0.05);
+    }
+
+    #[test]
+    fn test_expected_output_covariance() {
+        let mix = toy_mix();
+        let covariance = mix.expected_output_covariance();
+        approx::assert_relative_eq!(covariance, covariance.transpose());
+        approx::assert_relative_eq!(
+            covariance.diagonal(),
+            mix.expected_output_variance(),
+            max_relative = 1e-10
+        );
+
+        let dataset = mix.sample(100_000, 0.0);
+        approx::assert_abs_diff_eq!(covariance, dataset.sample_covariance(), epsilon = 0.02);
+    }
+
+    #[test]
+    fn test_sample_n_per_component() {
+        let mix = toy_mix();
+        let (dataset, labels) = mix.sample_n_per_component(&[100, 30], 0.0, 5);
+        assert_eq!(dataset.len(), 130);
+        assert_eq!(labels.iter().filter(|&&label| label == 0).count(), 100);
+        assert_eq!(labels.iter().filter(|&&label| label == 1).count(), 30);
+
+        for (sample, &label) in dataset.iter().zip(&labels) {
+            let model = &mix.models()[label];
+            let mahalanobis = model
+                .output_covariance()
+                .quadratic_form(&(sample.data_vector() - model.output_mean()));
+            // Beyond the 99.9999% quantile (30.7) of the chi-squared with 3 degrees of freedom.
+            assert!(mahalanobis < 35.0, "{mahalanobis}");
+        }
+    }
+
+    #[test]
+    fn test_evaluate_on_grid() {
+        let mix = PPCAMix::new(
+            vec![
+                PPCAModel::new(0.5, dmatrix![1.0; 1.0], dvector![1.0, 0.0]),
+                PPCAModel::new(0.3, dmatrix![0.5; -1.0], dvector![-1.0, 2.0]),
+            ],
+            dvector![0.4f64.ln(), 0.6f64.ln()],
+        );
+        let (x_grid, y_grid, log_densities) = mix.evaluate_on_grid((-8.0, 8.0), (-8.0, 8.0), 201);
+
+        assert_eq!(log_densities.shape(), (201, 201));
+        assert_eq!(x_grid[(10, 0)], -8.0);
+        assert_eq!(x_grid[(10, 200)], 8.0);
+        assert_eq!(y_grid[(0, 10)], -8.0);
+        assert_eq!(y_grid[(200, 10)], 8.0);
+
+        let cell_area = (16.0 / 200.0f64).powi(2);
+        let integral = log_densities.map(f64::exp).sum() * cell_area;
+        approx::assert_abs_diff_eq!(integral, 1.0, epsilon = 1e-3);
+    }
+
+    #[test]
+    fn test_degrees_of_freedom_approx() {
+        let mix = toy_mix();
+        let dataset = mix.sample_with_rng(200, 0.0, &mut StdRng::seed_from_u64(0));
+        let component_dofs = mix
+            .models()
+            .iter()
+            .map(|model| model.degrees_of_freedom_observation(&dataset.data[0]) as f64)
+            .collect::<Vec<_>>();
+        assert_eq!(component_dofs, vec![1.0, 2.0]);
+
+        for sample in dataset.iter() {
+            let dof = mix.degrees_of_freedom_approx(sample);
+            assert!((1.0 - 1e-12..=2.0 + 1e-12).contains(&dof), "{dof}");
+        }
+
+        let single = PPCAMix::new(vec![mix.models()[1].clone()], dvector![0.0]);
+        for sample in dataset.iter() {
+            approx::assert_relative_eq!(
+                single.degrees_of_freedom_approx(sample),
+                mix.models()[1].degrees_of_freedom_observation(sample) as f64
+            );
+        }
+    }
+
+    #[test]
+    fn test_responsibilities() {
+        let mix = toy_mix();
+        let dataset = mix.sample(200, 0.2);
+        let responsibilities = mix.compute_responsibilities(&dataset);
+
+        assert_eq!(responsibilities.matrix().shape(), (200, 2));
+        approx::assert_relative_eq!(
+            *responsibilities.matrix(),
+            mix.infer_cluster(&dataset).map(f64::exp)
+        );
+        assert_eq!(
+            responsibilities.hard_assignments(),
+            mix.hard_cluster_assignments(&dataset)
+        );
+
+        let entropy = responsibilities.entropy();
+        assert!(entropy
+            .iter()
+            .all(|&entropy| (-1e-12..=2.0f64.ln() + 1e-12).contains(&entropy)));
+
+        let n_effective = responsibilities.n_effective_samples_per_component();
+        approx::assert_relative_eq!(n_effective.sum(), 200.0, max_relative = 1e-10);
+        for k in 0..2 {
+            let weighted = responsibilities.weighted_dataset(k);
+            assert_eq!(weighted.len(), 200);
+            approx::assert_relative_eq!(
+                weighted.weights.iter().sum::<f64>(),
+                n_effective[k],
+                max_relative = 1e-10
+            );
+        }
+
+        let empty = mix.compute_responsibilities(&Dataset::new(vec![]));
+        assert_eq!(empty.matrix().shape(), (0, 2));
+        assert!(empty.hard_assignments().is_empty());
+        assert_eq!(empty.n_effective_samples_per_component(), DVector::zeros(2));
+    }
+
+    #[test]
+    fn test_fit_monitor() {
+        let mix = toy_mix();
+        let dataset = mix.sample(200, 0.2);
+        let init = PPCAMix::init(2, 2, &dataset);
+        let config = PPCAFitConfig::default().max_iter(10).tol(0.0);
+
+        let (receiver, handle) = init.fit_monitor(&dataset, &config);
+        let reports = receiver.iter().collect::<Vec<_>>();
+        let monitored = handle.join().expect("fit thread did not panic");
+        let (fitted, llks) = init.fit(&dataset, &config);
+
+        assert_eq!(reports.len(), llks.len() - 1);
+        for (idx, report) in reports.iter().enumerate() {
+            assert_eq!(report.iter, idx + 1);
+            approx::assert_relative_eq!(report.llk, llks[idx + 1], max_relative = 1e-10);
+            approx::assert_relative_eq!(
+                report.delta_llk,
+                llks[idx + 1] - llks[idx],
+                epsilon = 1e-6
+            );
+        }
+        assert!(reports
+            .windows(2)
+            .all(|pair| pair[0].elapsed_secs <= pair[1].elapsed_secs));
+        approx::assert_relative_eq!(
+            monitored.param_vector(),
+            fitted.param_vector(),
+            max_relative = 1e-8
+        );
+    }
+
+    #[test]
+    fn test_interpolate() {
+        let mix = toy_mix();
+        let from = MaskedSample::unmasked(dvector![1.0, 1.0, 1.5]);
+        let to = MaskedSample::unmasked(dvector![2.0, 3.0, 0.0]);
+        let path = mix.interpolate(&from, &to, 3);
+        assert_eq!(path.len(), 3);
+
+        let from_model =
+            &mix.models()[mix.hard_cluster_assignments(&Dataset::new(vec![from.clone()]))[0]];
+        let to_model =
+            &mix.models()[mix.hard_cluster_assignments(&Dataset::new(vec![to.clone()]))[0]];
+        let points = path
+            .iter()
+            .map(MaskedSample::data_vector)
+            .collect::<Vec<_>>();
+        approx::assert_relative_eq!(points[0], from_model.smooth_one(&from).data_vector());
+        approx::assert_relative_eq!(points[2], to_model.smooth_one(&to).data_vector());
+        approx::assert_relative_eq!(points[1], (&points[0] + &points[2]) / 2.0);
+    }
+
+    #[test]
+    fn test_project_to_2d_with_labels() {
+        let mix = toy_mix();
+        let mix = PPCAMix::new(
+            vec![
+                mix.models()[0].clone(),
+                mix.models()[1].clone_with_state_size(2, &mut StdRng::seed_from_u64(0)),
+            ],
+            mix.log_weights().clone(),
+        );
+        let dataset = mix.sample(40, 0.2);
+        let (projected, labels) = mix.project_to_2d_with_labels(&dataset);
+
+        assert_eq!(projected.shape(), (40, 2));
+        assert_eq!(labels, mix.hard_cluster_assignments(&dataset));
+        let component_projections = mix
+            .models()
+            .iter()
+            .map(|model| model.project_to_2d(&dataset))
+            .collect::<Vec<_>>();
+        for (idx, &label) in labels.iter().enumerate() {
+            approx::assert_relative_eq!(projected.row(idx), component_projections[label].row(idx));
+        }
+    }
+
+    #[test]
+    fn test_component_pairwise_kl() {
+        let mix = toy_mix();
+        let kl = mix.component_pairwise_kl();
+
+        assert_eq!(kl.shape(), (2, 2));
+        assert_eq!(kl.diagonal(), DVector::zeros(2));
+        assert!(kl.iter().all(|&divergence| divergence >= 0.0));
+        approx::assert_relative_eq!(kl[(0, 1)], mix.models()[0].kl_divergence(&mix.models()[1]));
+        assert!((kl[(0, 1)] - kl[(1, 0)]).abs() > 1.0);
+    }
+
+    #[test]
+    fn test_kl_components_to_mixture() {
+        let mix = toy_mix();
+        let kl = mix.kl_components_to_mixture(1_000, 0);
+
+        assert_eq!(kl.len(), 2);
+        for (&divergence, &log_weight) in kl.iter().zip(mix.log_weights()) {
+            assert!(divergence >= 0.0);
+            assert!(divergence <= -log_weight + 1e-12);
+        }
+
+        // With the components far apart, each sample is explained by its component only.
+        let mut far = mix.models()[1].clone();
+        far.set_mean(dvector![100.0, 100.0, 100.0]);
+        let far_mix = PPCAMix::new(
+            vec![mix.models()[0].clone(), far],
+            mix.log_weights().clone(),
+        );
+        approx::assert_relative_eq!(
+            far_mix.kl_components_to_mixture(100, 0),
+            -mix.log_weights(),
+            epsilon = 1e-9
+        );
+
+        let single = PPCAMix::new(vec![mix.models()[0].clone()], dvector![0.0]);
+        approx::assert_relative_eq!(single.kl_components_to_mixture(100, 0)[0], 0.0);
+    }
+
+    #[test]
+    fn test_average_llk_per_component() {
+        let mix = toy_mix();
+        let dataset = mix.sample(500, 0.2);
+        let average_llks = mix.average_llk_per_component(&dataset);
+        assert_eq!(average_llks.len(), 2);
+
+        // `log p(x) = Σ_k p(k|x) (log p(x|k) + log w_k) + H[p(k|x)]`, summed over samples.
+        let responsibilities = mix.compute_responsibilities(&dataset);
+        let sample_sizes = responsibilities.n_effective_samples_per_component();
+        let total = sample_sizes.dot(&(&average_llks + mix.log_weights()))
+            + responsibilities.entropy().sum();
+        approx::assert_relative_eq!(total, mix.llk(&dataset), max_relative = 1e-9);
+
+        let single = PPCAMix::new(vec![mix.models()[1].clone()], dvector![0.0]);
+        approx::assert_relative_eq!(
+            single.average_llk_per_component(&dataset)[0],
+            mix.models()[1].llk(&dataset) / dataset.len() as f64,
+            max_relative = 1e-9
+        );
+
+        // No component is responsible for any sample of an empty dataset.
+        let empty = mix.average_llk_per_component(&Dataset::new(vec![]));
+        assert_eq!(empty.len(), 2);
+        assert!(empty.iter().all(|llk| llk.is_nan()));
+    }
+
+    #[test]
+    fn test_posterior_sample() {
+        let mix = toy_mix();
+        // Ambiguous between the two components.
+        let sample = MaskedSample::new(
+            dvector![0.0, 1.0, 0.0],
+            Mask(BitVec::from_iter([false, true, false])),
+        );
+        let smoothed = mix
+            .smooth(&Dataset::new(vec![sample.clone()]))
+            .iter()
+            .next()
+            .expect("dataset has one sample")
+            .data_vector();
+
+        let draws = mix.posterior_sample(&sample, 20_000, 0);
+        assert_eq!(draws.len(), 20_000);
+        assert!(draws.iter().all(|draw| draw.len() == 3));
+        let draw_mean = draws.iter().sum::<DVector<f64>>() / draws.len() as f64;
+        approx::assert_abs_diff_eq!(draw_mean, smoothed, epsilon = 0.05);
+
+        assert_eq!(draws, mix.posterior_sample(&sample, 20_000, 0));
+    }
+
+    #[test]
+    fn test_predict_from_partial() {
+        let mix = PPCAMix::new(
+            vec![
+                PPCAModel::new(
+                    0.1,
+                    dmatrix![1.0, 1.0, 0.0].transpose(),
+                    dvector![0.0, 0.0, 0.0],
+                ),
+                PPCAModel::new(
+                    0.1,
+                    dmatrix![1.0, -1.0, 0.0].transpose(),
+                    dvector![10.0, 10.0, 10.0],
+                ),
+            ],
+            dvector![0.5f64.ln(), 0.5f64.ln()],
+        );
+
+        for (model, center) in mix.models().iter().zip([0.0, 10.0]) {
+            let observed = dvector![center + 0.3, center];
+            let (mean, std) = mix.predict_from_partial(&[0, 2], &observed, &[1]);
+            let (component_mean, component_std) =
+                model.predict_from_partial(&[0, 2], &observed, &[1]);
+            approx::assert_relative_eq!(mean, component_mean, epsilon = 1e-6);
+            approx::assert_relative_eq!(std, component_std, epsilon = 1e-6);
+        }
+
+        // Halfway, both components contribute and the variance grows accordingly.
+        let (_, std) = mix.predict_from_partial(&[0], &dvector![5.0], &[1, 2]);
+        assert!(std[1] > 4.0);
+    }
+
+    #[test]
+    fn test_cross_validate_n_components() {
+        let (_, dataset) = PPCAMix::generate_synthetic(4, &[1, 1, 1], 3, 600, 0.2, 21);
+        let scores = PPCAMix::cross_validate_n_components(&dataset, &[1, 2, 3, 4], 1, 3, 4, 5);
+
+        assert_eq!(
+            scores.iter().map(|&(count, _)| count).collect::<Vec<_>>(),
+            vec![1, 2, 3, 4]
+        );
+        let best = scores
+            .iter()
+            .max_by(|(_, llk), (_, other_llk)| llk.total_cmp(other_llk))
+            .expect("there are candidates")
+            .0;
+        assert_eq!(best, 3);
+
+        // The held-out log-likelihood rises up to the true number of components and an
+        // extra component does not improve it further.
+        assert!(scores[1].1 > scores[0].1);
+        assert!(scores[2].1 > scores[1].1);
+    }
+
+    #[test]
+    fn test_simulate_em_trajectory() {
+        let (_, dataset) = PPCAMix::generate_synthetic(5, &[1, 1, 1, 1], 4, 400, 0.3, 8);
+        let trajectory = PPCAMix::init_with_rng(4, 1, &dataset, &mut StdRng::seed_from_u64(3))
+            .simulate_em_trajectory(&dataset, 10);
+        assert_eq!(trajectory.len(), 11);
+
+        for pair in trajectory.windows(2) {
+            assert!(pair[1].1 >= pair[0].1 - 1e-6 * pair[0].1.abs());
+        }
+
+        let average_mean_distance = |mix: &PPCAMix| {
+            let means = mix
+                .models()
+                .iter()
+                .map(PPCAModel::output_mean)
+                .collect::<Vec<_>>();
+            (0..4)
+                .flat_map(|i| (0..i).map(move |j| (i, j)))
+                .map(|(i, j)| (means[i] - means[j]).norm())
+                .sum::<f64>()
+                / 6.0
+        };
+        let mean_assignment_entropy =
+            |mix: &PPCAMix| mix.compute_responsibilities(&dataset).entropy().mean();
+
+        let (first, _) = &trajectory[0];
+        let (last, _) = &trajectory[10];
+        assert!(average_mean_distance(last) > average_mean_distance(first) + 1.0);
+        assert!(mean_assignment_entropy(last) < mean_assignment_entropy(first));
+    }
+
+    #[test]
+    fn test_json_round_trip() {
+        use std::fs::File;
+        use std::io::{BufReader, BufWriter};
+
+        let mix = toy_mix();
+        let path = std::env::temp_dir().join(format!("ppca_mix_{}.json", std::process::id()));
+        mix.to_json_writer(BufWriter::new(
+            File::create(&path).expect("can create file"),
+        ))
+        .expect("can write mixture");
+        let read =
+            PPCAMix::from_json_reader(BufReader::new(File::open(&path).expect("can open file")))
+                .expect("can read mixture");
+        std::fs::remove_file(&path).expect("can remove file");
+
+        assert_eq!(read.state_sizes(), mix.state_sizes());
+        approx::assert_relative_eq!(read.param_vector(), mix.param_vector());
+    }
+
+    #[test]
+    fn test_from_csv() {
+        let mix = toy_mix();
+        let dir = std::env::temp_dir();
+        let path = |name: String| dir.join(format!("ppca_mix_{}_{name}.csv", std::process::id()));
+        let to_csv_row = |values: &[f64]| {
+            values
+                .iter()
+                .map(f64::to_string)
+                .collect::<Vec<_>>()
+                .join(",")
+        };
+        let to_csv = |matrix: &DMatrix<f64>| {
+            matrix
+                .row_iter()
+                .map(|row| to_csv_row(row.transpose().as_slice()))
+                .collect::<Vec<_>>()
+                .join("\n")
+        };
+
+        let mut paths = vec![];
+        for (k, model) in mix.models().iter().enumerate() {
+            let component_paths = (
+                path(format!("loadings_{k}")),
+                path(format!("mean_{k}")),
+                path(format!("noise_{k}")),
+            );
+            std::fs::write(&component_paths.0, to_csv(model.loadings())).expect("can write file");
+            std::fs::write(
+                &component_paths.1,
+                to_csv_row(model.output_mean().as_slice()),
+            )
+            .expect("can write file");
+            std::fs::write(&component_paths.2, model.noise_variance().to_string())
+                .expect("can write file");
+            paths.push(component_paths);
+        }
+        let weights_path = path("weights".to_owned());
+        std::fs::write(
+            &weights_path,
+            to_csv_row(mix.log_weights().map(f64::exp).as_slice()),
+        )
+        .expect("can write file");
+
+        let component_paths = paths
+            .iter()
+            .map(|(loadings, mean, noise)| (loadings.as_path(), mean.as_path(), noise.as_path()))
+            .collect::<Vec<_>>();
+        let read = PPCAMix::from_csv(&component_paths, &weights_path).expect("can read mixture");
+        approx::assert_relative_eq!(read.param_vector(), mix.param_vector(), epsilon = 1e-12);
+
+        std::fs::write(&weights_path, "1.0").expect("can write file");
+        assert!(matches!(
+            PPCAMix::from_csv(&component_paths, &weights_path),
+            Err(CsvError::Invalid(
+                ValidationError::WeightCountMismatch { .. }
+            ))
+        ));
+
+        for (loadings, mean, noise) in paths {
+            for path in [loadings, mean, noise] {
+                std::fs::remove_file(path).expect("can remove file");
+            }
+        }
+        std::fs::remove_file(weights_path).expect("can remove file");
+    }
+
+    #[test]
+    fn test_validate() {
+        let mix = toy_mix();
+        assert_eq!(mix.validate(), Ok(()));
+
+        let mut unnormalized = mix.clone();
+        unnormalized.log_weights = dvector![0.0, 0.0];
+        assert_eq!(
+            unnormalized.validate(),
+            Err(ValidationError::WeightsNotNormalized(2.0))
+        );
+
+        let mut missing_weight = mix.clone();
+        missing_weight.log_weights = dvector![0.0];
+        assert_eq!(
+            missing_weight.validate(),
+            Err(ValidationError::WeightCountMismatch {
+                n_components: 2,
+                n_weights: 1
+            })
+        );
+
+        let mut wrong_size = mix.clone();
+        wrong_size.models[1] = PPCAModel::new(0.1, dmatrix![1.0; 1.0], dvector![0.0, 0.0]);
+        assert_eq!(
+            wrong_size.validate(),
+            Err(ValidationError::ComponentOutputSizeMismatch {
+                component: 1,
+                expected: 3,
+                found: 2
+            })
+        );
+
+        let mut invalid = mix.clone();
+        invalid.models[0] = PPCAModel::new(0.0, dmatrix![1.0; 1.0; 1.0], dvector![0.0, 0.0, 0.0]);
+        assert_eq!(
+            invalid.validate(),
+            Err(ValidationError::InvalidComponent {
+                component: 0,
+                error: Box::new(ValidationError::NonPositiveNoise(0.0))
+            })
+        );
+    }
+
+    #[test]
+    fn test_sample_with_rng() {
+        let mix = toy_mix();
+        let dataset = mix.sample_with_rng(100, 0.2, &mut StdRng::seed_from_u64(4));
+        assert_eq!(dataset.len(), 100);
+
+        let same = mix.sample_with_seed(100, 0.2, 4);
+        for (sample, other) in dataset.iter().zip(same.iter()) {
+            assert_eq!(sample.mask().0, other.mask().0);
+            assert_eq!(sample.data_vector(), other.data_vector());
+        }
+
+        let other = mix.sample_with_seed(100, 0.2, 5);
+        assert!(dataset
+            .iter()
+            .zip(other.iter())
+            .any(|(sample, other)| sample.data_vector() != other.data_vector()));
+    }
+
+    #[test]
+    fn test_dimension_importance() {
+        let mix = toy_mix();
+        let single = PPCAMix::new(vec![mix.models()[1].clone()], dvector![0.0]);
+        approx::assert_relative_eq!(
+            single.dimension_importance(),
+            mix.models()[1].dimension_importance()
+        );
+
+        let expected = 0.33333 * mix.models()[0].dimension_importance()
+            + 0.66667 * mix.models()[1].dimension_importance();
+        approx::assert_relative_eq!(mix.dimension_importance(), expected, max_relative = 1e-10);
+    }
+
+    #[test]
+    fn test_component_sample_sizes() {
+        let mix = toy_mix();
+        let dataset = mix.sample(300, 0.0);
+        let sizes = mix.component_sample_sizes(&dataset);
+        assert_eq!(sizes.len(), 2);
+        approx::assert_relative_eq!(sizes.sum(), 300.0, max_relative = 1e-10);
+
+        let (single_component, _) = mix.sample_n_per_component(&[0, 200], 0.0, 3);
+        let sizes = mix.component_sample_sizes(&single_component);
+        assert!(sizes[0] < 0.05 * 200.0);
+        assert!(sizes[1] > 0.95 * 200.0);
+    }
+
+    #[test]
+    fn test_log_partition_function_bounds() {
+        let mix = toy_mix();
+        let log_normalizers = mix
+            .models()
+            .iter()
+            .map(PPCAModel::log_normalizer)
+            .collect::<Vec<_>>();
+        let exact = log_sum_exp(mix.log_weights() + DVector::from(log_normalizers.clone()));
+        let jensen = mix
+            .log_weights()
+            .iter()
+            .zip(&log_normalizers)
+            .map(|(&log_weight, &log_normalizer)| log_weight.exp() * log_normalizer)
+            .sum::<f64>();
+
+        let lower = mix.log_partition_function_lower();
+        let upper = mix.log_partition_function_upper();
+        approx::assert_relative_eq!(lower, jensen, max_relative = 1e-12);
+        assert!(lower <= exact);
+        assert!(exact <= upper);
+    }
+
+    #[test]
+    fn test_state_sizes() {
+        let mix = toy_mix();
+        assert_eq!(mix.n_components(), 2);
+        assert_eq!(mix.state_sizes(), vec![2, 1]);
+        assert_eq!(mix.max_state_size(), 2);
+        assert_eq!(mix.min_state_size(), 1);
+        assert_eq!(mix.output_size(), mix.models()[0].output_size());
+        assert_eq!(
+            mix.state_sizes(),
+            mix.models()
+                .iter()
+                .map(|model| model.state_size())
+                .collect::<Vec<_>>()
+        );
+    }
+}
@@ -36,10 +36,17 @@ impl<'a> OutputCovariance<'a> {
     //     }
     // }
 
-    // pub(crate) fn matrix(&self) -> DMatrix<f64> {
-    //     DMatrix::identity(self.output_size(), self.output_size()) * self.isotropic_noise.powi(2)
-    //         + &*self.transform * self.transform.transpose()
-    // }
+    pub(crate) fn matrix(&self) -> DMatrix<f64> {
+        DMatrix::identity(self.output_size(), self.output_size()) * self.isotropic_noise.powi(2)
+            + &*self.transform * self.transform.transpose()
+    }
+
+    /// The matrix `Pᵀ C P`, without ever building the full covariance `C`.
+    pub(crate) fn projected_matrix(&self, projection: &DMatrix<f64>) -> DMatrix<f64> {
+        let projected_transform = projection.transpose() * &*self.transform;
+        projection.transpose() * projection * self.isotropic_noise.powi(2)
+            + &projected_transform * projected_transform.transpose()
+    }
 
     pub(crate) fn inner_product(&self) -> DMatrix<f64> {
         self.transform.transpose() * &*self.transform
@@ -0,0 +1,393 @@
+//! A C ABI for the key operations of `PPCAModel`. Models are handed out as opaque
+//! pointers created by `ppca_model_new` or `ppca_model_deserialize_json` and released by
+//! `ppca_model_free`. All matrices are dense, row-major arrays of `double`. The matching
+//! declarations are in `ppca.h`.
+
+use bit_vec::BitVec;
+use nalgebra::{DMatrix, DVector};
+use std::os::raw::c_char;
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+use std::slice;
+
+use crate::ppca_model::{Dataset, MaskedSample, PPCAModel};
+use crate::utils::Mask;
+
+const PPCA_OK: i32 = 0;
+const PPCA_ERROR: i32 = -1;
+
+/// Runs `body`, returning `fallback` if it panics: unwinding out of an `extern "C"`
+/// function would abort the host process.
+fn catch_panic<T>(fallback: T, body: impl FnOnce() -> T) -> T {
+    panic::catch_unwind(AssertUnwindSafe(body)).unwrap_or(fallback)
+}
+
+/// Reads a row-major matrix into a dataset. If no mask is given, non-finite values
+/// (`inf`s and `nan`) are treated as masked, just like in the Python bindings.
+fn read_dataset(data: &[f64], mask: Option<&[u8]>, n_rows: usize, n_cols: usize) -> Dataset {
+    (0..n_rows)
+        .map(|row| {
+            let values = &data[row * n_cols..(row + 1) * n_cols];
+            let mask = Mask(match mask {
+                Some(mask) => mask[row * n_cols..(row + 1) * n_cols]
+                    .iter()
+                    .map(|&selected| selected != 0)
+                    .collect::<BitVec>(),
+                None => values.iter().map(|value| value.is_finite()).collect(),
+            });
+            MaskedSample::new(mask.fillna(&DVector::from_column_slice(values)), mask)
+        })
+        .collect()
+}
+
+/// Creates a new model from its parameters, returning null on invalid input, i.e., on
+/// null pointers, a noise which is not positive and finite, sizes whose product
+/// overflows or non-finite parameters. The `transform` is an `output_size x state_size`
+/// matrix and `mean` has `output_size` entries.
+///
+/// # Safety
+///
+/// `transform` and `mean` must point to arrays of the sizes described above.
+#[no_mangle]
+pub unsafe extern "C" fn ppca_model_new(
+    isotropic_noise: f64,
+    transform: *const f64,
+    mean: *const f64,
+    output_size: usize,
+    state_size: usize,
+) -> *mut PPCAModel {
+    catch_panic(ptr::null_mut(), || {
+        if transform.is_null() || mean.is_null() {
+            return ptr::null_mut();
+        }
+        if !isotropic_noise.is_finite() || isotropic_noise <= 0.0 {
+            return ptr::null_mut();
+        }
+        let Some(transform_len) = output_size.checked_mul(state_size) else {
+            return ptr::null_mut();
+        };
+
+        let transform = slice::from_raw_parts(transform, transform_len);
+        let mean = slice::from_raw_parts(mean, output_size);
+        let model = PPCAModel::new(
+            isotropic_noise,
+            DMatrix::from_row_slice(output_size, state_size, transform),
+            DVector::from_column_slice(mean),
+        );
+
+        if model.validate().is_err() {
+            return ptr::null_mut();
+        }
+
+        Box::into_raw(Box::new(model))
+    })
+}
+
+/// Frees a model created by this library. Passing null is a no-op.
+///
+/// # Safety
+///
+/// `model` must be null or a pointer returned by this library that was not yet freed.
+#[no_mangle]
+pub unsafe extern "C" fn ppca_model_free(model: *mut PPCAModel) {
+    catch_panic((), || {
+        if !model.is_null() {
+            drop(Box::from_raw(model));
+        }
+    })
+}
+
+/// Writes the log-probability of each of the `n_rows` samples in `data` into `out`.
+/// Non-finite values in `data` are treated as masked. Returns `0` on success and `-1`
+/// on null pointers or if `n_cols` is not the output size of the model.
+///
+/// # Safety
+///
+/// `model` must be a valid model, `data` must have `n_rows * n_cols` entries and `out`
+/// must have room for `n_rows` entries.
+#[no_mangle]
+pub unsafe extern "C" fn ppca_model_llk(
+    model: *const PPCAModel,
+    data: *const f64,
+    n_rows: usize,
+    n_cols: usize,
+    out: *mut f64,
+) -> i32 {
+    catch_panic(PPCA_ERROR, || {
+        let Some(model) = model.as_ref() else {
+            return PPCA_ERROR;
+        };
+        if data.is_null() || out.is_null() || n_cols != model.output_size() {
+            return PPCA_ERROR;
+        }
+        let Some(data_len) = n_rows.checked_mul(n_cols) else {
+            return PPCA_ERROR;
+        };
+
+        let data = slice::from_raw_parts(data, data_len);
+        let out = slice::from_raw_parts_mut(out, n_rows);
+        let llks = model.llks(&read_dataset(data, None, n_rows, n_cols));
+        out.copy_from_slice(llks.as_slice());
+
+        PPCA_OK
+    })
+}
+
+/// Writes the smoothed version of each of the `n_rows` samples in `data` into `out`.
+/// An entry is observed if its corresponding entry in `mask` is non-zero. Returns `0`
+/// on success and `-1` on null pointers or if `n_cols` is not the output size of the
+/// model.
+///
+/// # Safety
+///
+/// `model` must be a valid model and `data`, `mask` and `out` must all have
+/// `n_rows * n_cols` entries.
+#[no_mangle]
+pub unsafe extern "C" fn ppca_model_smooth(
+    model: *const PPCAModel,
+    data: *const f64,
+    mask: *const u8,
+    n_rows: usize,
+    n_cols: usize,
+    out: *mut f64,
+) -> i32 {
+    catch_panic(PPCA_ERROR, || {
+        let Some(model) = model.as_ref() else {
+            return PPCA_ERROR;
+        };
+        if data.is_null() || mask.is_null() || out.is_null() || n_cols != model.output_size() {
+            return PPCA_ERROR;
+        }
+        let Some(data_len) = n_rows.checked_mul(n_cols) else {
+            return PPCA_ERROR;
+        };
+
+        let data = slice::from_raw_parts(data, data_len);
+        let mask = slice::from_raw_parts(mask, data_len);
+        let out = slice::from_raw_parts_mut(out, data_len);
+        let smoothed = model.smooth(&read_dataset(data, Some(mask), n_rows, n_cols));
+
+        for (row, sample) in out.chunks_mut(n_cols).zip(&*smoothed.data) {
+            row.copy_from_slice(sample.data_vector().as_slice());
+        }
+
+        PPCA_OK
+    })
+}
+
+/// Serializes the model as JSON into `out_buf`, in the spirit of `snprintf`: the
+/// return value is the length of the JSON string (without the terminating null byte)
+/// and the string is only written if `buf_len` is big enough to hold it together with
+/// the null byte. Call it with a null buffer to query the needed size. Returns `-1` if
+/// the model cannot be serialized.
+///
+/// # Safety
+///
+/// `model` must be a valid model and `out_buf` must be null or have room for `buf_len`
+/// bytes.
+#[no_mangle]
+pub unsafe extern "C" fn ppca_model_serialize_json(
+    model: *const PPCAModel,
+    out_buf: *mut c_char,
+    buf_len: usize,
+) -> i64 {
+    catch_panic(-1, || {
+        let Some(model) = model.as_ref() else {
+            return -1;
+        };
+        let Ok(json) = serde_json::to_string(model) else {
+            return -1;
+        };
+        let bytes = json.as_bytes();
+
+        if !out_buf.is_null() && bytes.len() < buf_len {
+            ptr::copy_nonoverlapping(bytes.as_ptr(), out_buf as *mut u8, bytes.len());
+            *out_buf.add(bytes.len()) = 0;
+        }
+
+        bytes.len() as i64
+    })
+}
+
+/// Creates a model from the JSON written by `ppca_model_serialize_json`, returning null
+/// if the JSON is invalid or does not describe a valid model (see `PPCAModel::validate`).
+///
+/// # Safety
+///
+/// `json` must point to `len` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn ppca_model_deserialize_json(
+    json: *const c_char,
+    len: usize,
+) -> *mut PPCAModel {
+    catch_panic(ptr::null_mut(), || {
+        if json.is_null() {
+            return ptr::null_mut();
+        }
+
+        let bytes = slice::from_raw_parts(json as *const u8, len);
+        match serde_json::from_slice::<PPCAModel>(bytes) {
+            Ok(model) if model.validate().is_ok() => Box::into_raw(Box::new(model)),
+            _ => ptr::null_mut(),
+        }
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // A 3x2 transform, row-major, with `σ = 0.1` and mean `[0, 1, 0]`.
+    const TRANSFORM: [f64; 6] = [1.0, 1.0, 1.0, 0.0, 0.0, 1.0];
+    const MEAN: [f64; 3] = [0.0, 1.0, 0.0];
+
+    unsafe fn toy_model() -> *mut PPCAModel {
+        ppca_model_new(0.1, TRANSFORM.as_ptr(), MEAN.as_ptr(), 3, 2)
+    }
+
+    #[test]
+    fn test_model_new() {
+        unsafe {
+            let model = toy_model();
+            assert!(!model.is_null());
+            assert_eq!((*model).output_size(), 3);
+            assert_eq!((*model).state_size(), 2);
+            ppca_model_free(model);
+
+            assert!(ppca_model_new(0.1, ptr::null(), MEAN.as_ptr(), 3, 2).is_null());
+            assert!(ppca_model_new(0.1, TRANSFORM.as_ptr(), ptr::null(), 3, 2).is_null());
+            for noise in [0.0, -0.1, f64::NAN, f64::INFINITY] {
+                assert!(ppca_model_new(noise, TRANSFORM.as_ptr(), MEAN.as_ptr(), 3, 2).is_null());
+            }
+            assert!(
+                ppca_model_new(0.1, TRANSFORM.as_ptr(), MEAN.as_ptr(), usize::MAX, 2).is_null()
+            );
+            let nan_mean = [0.0, f64::NAN, 0.0];
+            assert!(ppca_model_new(0.1, TRANSFORM.as_ptr(), nan_mean.as_ptr(), 3, 2).is_null());
+
+            ppca_model_free(ptr::null_mut());
+        }
+    }
+
+    #[test]
+    fn test_model_llk() {
+        unsafe {
+            let model = toy_model();
+            let data = [0.0, 1.0, 0.0, 1.0, f64::NAN, 0.5];
+            let mut out = [0.0; 2];
+
+            assert_eq!(
+                ppca_model_llk(model, data.as_ptr(), 2, 3, out.as_mut_ptr()),
+                PPCA_OK
+            );
+            let dataset = read_dataset(&data, None, 2, 3);
+            approx::assert_relative_eq!(DVector::from_column_slice(&out), (*model).llks(&dataset));
+
+            assert_eq!(
+                ppca_model_llk(ptr::null(), data.as_ptr(), 2, 3, out.as_mut_ptr()),
+                PPCA_ERROR
+            );
+            assert_eq!(
+                ppca_model_llk(model, ptr::null(), 2, 3, out.as_mut_ptr()),
+                PPCA_ERROR
+            );
+            assert_eq!(
+                ppca_model_llk(model, data.as_ptr(), 2, 3, ptr::null_mut()),
+                PPCA_ERROR
+            );
+            assert_eq!(
+                ppca_model_llk(model, data.as_ptr(), 3, 2, out.as_mut_ptr()),
+                PPCA_ERROR
+            );
+            assert_eq!(
+                ppca_model_llk(model, data.as_ptr(), usize::MAX, 3, out.as_mut_ptr()),
+                PPCA_ERROR
+            );
+
+            ppca_model_free(model);
+        }
+    }
+
+    #[test]
+    fn test_model_smooth() {
+        unsafe {
+            let model = toy_model();
+            let data = [1.0, 1.0, 0.0, 0.0, 1.0, 0.5];
+            let mask = [1, 1, 1, 0, 1, 1];
+            let mut out = [0.0; 6];
+
+            assert_eq!(
+                ppca_model_smooth(model, data.as_ptr(), mask.as_ptr(), 2, 3, out.as_mut_ptr()),
+                PPCA_OK
+            );
+            let smoothed = (*model).smooth(&read_dataset(&data, Some(&mask), 2, 3));
+            approx::assert_relative_eq!(
+                DMatrix::from_row_slice(2, 3, &out),
+                smoothed.to_dense_matrix(0.0)
+            );
+
+            assert_eq!(
+                ppca_model_smooth(model, data.as_ptr(), ptr::null(), 2, 3, out.as_mut_ptr()),
+                PPCA_ERROR
+            );
+            assert_eq!(
+                ppca_model_smooth(
+                    model,
+                    data.as_ptr(),
+                    mask.as_ptr(),
+                    usize::MAX,
+                    3,
+                    out.as_mut_ptr()
+                ),
+                PPCA_ERROR
+            );
+
+            ppca_model_free(model);
+        }
+    }
+
+    #[test]
+    fn test_model_json_round_trip() {
+        unsafe {
+            let model = toy_model();
+            let len = ppca_model_serialize_json(model, ptr::null_mut(), 0);
+            assert!(len > 0);
+
+            let mut buf = vec![0 as c_char; len as usize + 1];
+            assert_eq!(
+                ppca_model_serialize_json(model, buf.as_mut_ptr(), buf.len()),
+                len
+            );
+            assert_eq!(buf[len as usize], 0);
+
+            let read = ppca_model_deserialize_json(buf.as_ptr(), len as usize);
+            assert!(!read.is_null());
+            approx::assert_relative_eq!((*read).param_vector(), (*model).param_vector());
+
+            assert_eq!(
+                ppca_model_serialize_json(ptr::null(), ptr::null_mut(), 0),
+                -1
+            );
+            assert!(ppca_model_deserialize_json(ptr::null(), 0).is_null());
+            let garbage = b"{\"not\": \"a model\"}";
+            assert!(
+                ppca_model_deserialize_json(garbage.as_ptr() as *const c_char, garbage.len())
+                    .is_null()
+            );
+
+            // Valid JSON for an invalid model: the mean is too short for the transform.
+            let invalid = PPCAModel::new(
+                0.1,
+                DMatrix::from_row_slice(3, 2, &TRANSFORM),
+                DVector::from_column_slice(&MEAN[..2]),
+            );
+            let json = serde_json::to_string(&invalid).expect("can serialize model");
+            assert!(
+                ppca_model_deserialize_json(json.as_ptr() as *const c_char, json.len()).is_null()
+            );
+
+            ppca_model_free(read);
+            ppca_model_free(model);
+        }
+    }
+}
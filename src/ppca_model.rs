@@ -1,17 +1,44 @@
 use bit_vec::BitVec;
 use nalgebra::{DMatrix, DVector};
 use rand::distributions::Distribution;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 use rand_distr::Bernoulli;
 use rayon::prelude::*;
 use serde_derive::{Deserialize, Serialize};
 use std::borrow::Cow;
+use std::io::{Read, Write};
+use std::path::Path;
 use std::sync::Arc;
 
+use crate::csv::{read_csv_matrix, read_csv_scalar, read_csv_vector, write_csv_matrix};
+use crate::error::{AffineTransformError, CsvError, DatasetError, IndexError, ValidationError};
+use crate::fit_config::PPCAFitConfig;
+use crate::math::standard_normal_quantile;
+use crate::mix::PPCAMix;
 use crate::output_covariance::OutputCovariance;
-use crate::utils::{standard_noise, standard_noise_matrix, Mask};
+use crate::utils::{standard_noise_matrix_with_rng, standard_noise_with_rng, Mask};
 
 const LN_2PI: f64 = 1.8378770664093453;
 
+/// Number of EM iterations used to refit the model on each training fold in
+/// `PPCAModel::optimal_shrinkage`.
+const SHRINKAGE_CV_ITERATIONS: usize = 10;
+/// Step of the grid of shrinkages tried by `PPCAModel::optimal_shrinkage`.
+const SHRINKAGE_CV_STEP: f64 = 0.05;
+/// Scale, relative to the isotropic noise, of the extra transform columns created by
+/// `PPCAModel::clone_with_state_size`.
+const CLONE_PADDING_SCALE: f64 = 1e-2;
+/// Largest output size for which `PPCAModel::covariance_distance` compares the full
+/// covariance matrices. Above it, both are compared in a random subspace of this size.
+const COVARIANCE_DISTANCE_MAX_DIMENSION: usize = 500;
+/// Seed of the random subspace used by `PPCAModel::covariance_distance`, fixed so that
+/// the diagnostic is reproducible.
+const COVARIANCE_DISTANCE_SEED: u64 = 0;
+/// Largest relative deviation of `Aᵀ A` from a multiple of the identity for which
+/// `PPCAModel::apply_affine_transform` accepts `A` as a scaled orthogonal matrix.
+const AFFINE_ORTHOGONALITY_TOL: f64 = 1e-9;
+
 #[derive(Debug, Clone)]
 pub struct MaskedSample {
     data: DVector<f64>,
@@ -51,6 +78,52 @@ impl MaskedSample {
             .collect::<Vec<_>>()
             .into()
     }
+
+    /// The dimension of the output space of this sample, observed or not.
+    pub fn dimension(&self) -> usize {
+        self.mask.0.len()
+    }
+
+    pub fn n_observed(&self) -> usize {
+        self.mask.0.iter().filter(|&selected| selected).count()
+    }
+
+    pub fn n_missing(&self) -> usize {
+        self.dimension() - self.n_observed()
+    }
+
+    /// A sample of dimension `output_size` where only `observed_dims` are observed, with
+    /// the values `observed_values`, in the same order.
+    pub(crate) fn from_partial(
+        output_size: usize,
+        observed_dims: &[usize],
+        observed_values: &DVector<f64>,
+    ) -> MaskedSample {
+        assert_eq!(
+            observed_dims.len(),
+            observed_values.len(),
+            "need one value per observed dimension"
+        );
+        let mut data = DVector::zeros(output_size);
+        let mut mask = BitVec::from_elem(output_size, false);
+        for (&idx, &value) in observed_dims.iter().zip(observed_values) {
+            assert!(idx < output_size, "observed dimension out of range");
+            data[idx] = value;
+            mask.set(idx, true);
+        }
+
+        MaskedSample::new(data, Mask(mask))
+    }
+
+    /// The fraction of the dimensions of this sample which are missing, between 0 and 1.
+    /// Zero-dimensional samples have nothing missing.
+    pub fn missingness_fraction(&self) -> f64 {
+        if self.dimension() == 0 {
+            0.0
+        } else {
+            self.n_missing() as f64 / self.dimension() as f64
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -131,6 +204,34 @@ impl Dataset {
         }
     }
 
+    /// Attaches importance weights to the samples of this dataset, e.g., to correct for
+    /// samples drawn from a proposal distribution different from the target one. The
+    /// weights must be non-negative and finite.
+    pub fn with_importance_weights(&self, weights: DVector<f64>) -> Dataset {
+        assert_eq!(
+            weights.len(),
+            self.len(),
+            "need exactly one weight per sample"
+        );
+        assert!(
+            weights
+                .iter()
+                .all(|&weight| weight.is_finite() && weight >= 0.0),
+            "importance weights must be non-negative and finite"
+        );
+        self.with_weights(weights.iter().copied().collect())
+    }
+
+    /// Rescales the weights of this dataset so that they sum to the number of samples.
+    /// This leaves the relative importance of each sample unchanged, so fitting on the
+    /// balanced dataset gives the same model.
+    pub fn balance_by_weights(&self) -> Dataset {
+        let total_weight = self.weights.iter().sum::<f64>();
+        assert!(total_weight > 0.0, "weights must not be all zero");
+        let scale = self.len() as f64 / total_weight;
+        self.with_weights(self.weights.iter().map(|weight| weight * scale).collect())
+    }
+
     pub fn len(&self) -> usize {
         self.data.len()
     }
@@ -139,10 +240,273 @@ impl Dataset {
         self.data.is_empty()
     }
 
+    /// Iterates over the samples of this dataset, in order.
+    pub fn iter(&self) -> impl Iterator<Item = &MaskedSample> {
+        self.data.iter()
+    }
+
+    /// Iterates in parallel over the samples of this dataset.
+    pub fn par_iter(&self) -> impl IndexedParallelIterator<Item = &MaskedSample> {
+        self.data.par_iter()
+    }
+
     pub fn output_size(&self) -> Option<usize> {
         self.data.first().map(|sample| sample.mask().0.len())
     }
 
+    /// The weighted mean of each dimension over the samples where it is observed.
+    /// Dimensions which are never observed get a mean of zero.
+    pub fn sample_mean(&self) -> DVector<f64> {
+        let output_size = self.output_size().unwrap_or(0);
+        let (total, counts) = self
+            .data
+            .par_iter()
+            .zip(&self.weights)
+            .map(|(sample, &weight)| {
+                (
+                    weight * sample.mask.fillna(&sample.data),
+                    weight * sample.mask.as_vector(),
+                )
+            })
+            .reduce(
+                || (DVector::zeros(output_size), DVector::zeros(output_size)),
+                |(total, counts), (total_, counts_)| (total + total_, counts + counts_),
+            );
+
+        total.zip_map(
+            &counts,
+            |sum, count| if count > 0.0 { sum / count } else { 0.0 },
+        )
+    }
+
+    /// The weighted covariance matrix of the samples, estimated pairwise: each entry
+    /// `(i, j)` only uses the samples where both `i` and `j` are observed. Note that
+    /// this need not be positive semi-definite when there are lots of missing values.
+    pub fn sample_covariance(&self) -> DMatrix<f64> {
+        let output_size = self.output_size().unwrap_or(0);
+        let mean = self.sample_mean();
+        let (total, counts) = self
+            .data
+            .par_iter()
+            .zip(&self.weights)
+            .map(|(sample, &weight)| {
+                let centered = sample.mask.fillna(&(&sample.data - &mean));
+                let observed = sample.mask.as_vector();
+                (
+                    weight * &centered * centered.transpose(),
+                    weight * &observed * observed.transpose(),
+                )
+            })
+            .reduce(
+                || {
+                    (
+                        DMatrix::zeros(output_size, output_size),
+                        DMatrix::zeros(output_size, output_size),
+                    )
+                },
+                |(total, counts), (total_, counts_)| (total + total_, counts + counts_),
+            );
+
+        total.zip_map(
+            &counts,
+            |sum, count| if count > 0.0 { sum / count } else { 0.0 },
+        )
+    }
+
+    /// The weighted covariance `Pᵀ C P` of the samples projected by `projection`, with
+    /// the missing values filled by the sample mean. Unlike `Dataset::sample_covariance`,
+    /// this never builds the full covariance matrix.
+    pub(crate) fn projected_sample_covariance(&self, projection: &DMatrix<f64>) -> DMatrix<f64> {
+        let projected_size = projection.ncols();
+        let mean = self.sample_mean();
+        let total = self
+            .data
+            .par_iter()
+            .zip(&self.weights)
+            .map(|(sample, &weight)| {
+                let projected =
+                    projection.transpose() * sample.mask.fillna(&(&sample.data - &mean));
+                weight * &projected * projected.transpose()
+            })
+            .reduce(
+                || DMatrix::zeros(projected_size, projected_size),
+                |total, total_| total + total_,
+            );
+
+        total / self.weights.iter().sum::<f64>()
+    }
+
+    /// Masks each entry of each sample independently with probability
+    /// `mask_probability`, on top of the values which are already missing. The weights
+    /// are kept as they are.
+    pub fn with_mask_probability(&self, mask_probability: f64, seed: u64) -> Dataset {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let keep = Bernoulli::new(1.0 - mask_probability).expect("invalid mask probability");
+        self.data
+            .iter()
+            .zip(&self.weights)
+            .map(|(sample, &weight)| {
+                let mut mask = sample.mask.0.clone();
+                mask.and(&keep.sample_iter(&mut rng).take(mask.len()).collect());
+                let mask = Mask(mask);
+                (
+                    MaskedSample {
+                        data: mask.fillna(&sample.data),
+                        mask,
+                    },
+                    weight,
+                )
+            })
+            .collect()
+    }
+
+    /// Applies `f` to every observed value, e.g., `f64::ln_1p` to tame heavy tails, and
+    /// leaves the missing values and the masks as they are. The weights are kept too.
+    pub fn apply_function(&self, f: impl Fn(f64) -> f64 + Sync) -> Dataset {
+        let data = self
+            .data
+            .par_iter()
+            .map(|sample| MaskedSample {
+                data: sample.mask.choose(&sample.data.map(&f), &sample.data),
+                mask: sample.mask.clone(),
+            })
+            .collect::<Vec<_>>();
+
+        Dataset::new_with_weights(data, self.weights.clone())
+    }
+
+    /// Keeps only the dimensions `dims` of each sample, in that order, with their values
+    /// and missingness. The weights are kept as they are. Fails if a dimension is out of
+    /// bounds for some sample.
+    pub fn project_to_dims(&self, dims: &[usize]) -> Result<Dataset, IndexError> {
+        let data = self
+            .data
+            .iter()
+            .map(|sample| {
+                let output_size = sample.dimension();
+                if let Some(&index) = dims.iter().find(|&&index| index >= output_size) {
+                    return Err(IndexError { index, output_size });
+                }
+
+                Ok(MaskedSample {
+                    data: sample.data.select_rows(dims),
+                    mask: Mask(dims.iter().map(|&index| sample.mask.0[index]).collect()),
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Dataset::new_with_weights(data, self.weights.clone()))
+    }
+
+    /// The samples as the rows of a matrix, with the missing values set to
+    /// `fill_value`.
+    pub fn to_dense_matrix(&self, fill_value: f64) -> DMatrix<f64> {
+        let output_size = self.output_size().unwrap_or(0);
+        let mut matrix = DMatrix::zeros(self.len(), output_size);
+        for (mut row, sample) in matrix.row_iter_mut().zip(self.data.iter()) {
+            let filled = sample
+                .mask
+                .choose(&sample.data, &DVector::repeat(output_size, fill_value));
+            row.copy_from(&filled.transpose());
+        }
+
+        matrix
+    }
+
+    /// Checks that all samples have the same dimension as the first one and that the
+    /// weights are non-negative with a positive finite sum (for non-empty datasets).
+    pub fn validate(&self) -> Result<(), DatasetError> {
+        self.validate_dimension(self.output_size().unwrap_or(0))?;
+
+        for (sample, &weight) in self.weights.iter().enumerate() {
+            if weight.is_nan() || weight < 0.0 {
+                return Err(DatasetError::InvalidWeight { sample, weight });
+            }
+        }
+
+        let total_weight = self.weights.iter().sum::<f64>();
+        if !self.is_empty() && (total_weight <= 0.0 || !total_weight.is_finite()) {
+            return Err(DatasetError::InvalidTotalWeight(total_weight));
+        }
+
+        Ok(())
+    }
+
+    /// Checks that all samples have dimension `expected`.
+    pub(crate) fn validate_dimension(&self, expected: usize) -> Result<(), DatasetError> {
+        match self
+            .data
+            .iter()
+            .position(|sample| sample.dimension() != expected)
+        {
+            Some(sample) => Err(DatasetError::DimensionMismatch {
+                sample,
+                expected,
+                found: self.data[sample].dimension(),
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// Keeps only the samples with no missing values, together with their weights.
+    pub fn drop_missing(&self) -> Dataset {
+        self.data
+            .iter()
+            .zip(&self.weights)
+            .filter(|(sample, _)| sample.mask.0.all())
+            .map(|(sample, &weight)| (sample.clone(), weight))
+            .collect()
+    }
+
+    /// Alias of `Dataset::drop_missing`.
+    pub fn keep_fully_observed(&self) -> Dataset {
+        self.drop_missing()
+    }
+
+    /// The number of samples with no missing values.
+    pub fn n_fully_observed(&self) -> usize {
+        self.data
+            .iter()
+            .filter(|sample| sample.mask.0.all())
+            .count()
+    }
+
+    /// The number of samples with at least one missing value.
+    pub fn n_has_missing(&self) -> usize {
+        self.len() - self.n_fully_observed()
+    }
+
+    /// A `n_samples × output_size` matrix which is `true` where a value is missing.
+    pub fn missing_mask_matrix(&self) -> DMatrix<bool> {
+        DMatrix::from_fn(
+            self.len(),
+            self.output_size().unwrap_or(0),
+            |idx, dimension| !self.data[idx].mask.0[dimension],
+        )
+    }
+
+    /// Splits this dataset into `n_folds` interleaved folds for cross-validation,
+    /// returning a `(training, validation)` pair per fold. Sample `i` is held out in fold
+    /// `i % n_folds`. Weights are kept with their samples.
+    pub fn folds(&self, n_folds: usize) -> Vec<(Dataset, Dataset)> {
+        assert!(n_folds >= 2, "need at least two folds");
+        (0..n_folds)
+            .map(|fold| {
+                let (validation, training): (Vec<_>, Vec<_>) = self
+                    .data
+                    .iter()
+                    .cloned()
+                    .zip(self.weights.iter().copied())
+                    .enumerate()
+                    .partition(|(idx, _)| idx % n_folds == fold);
+                (
+                    training.into_iter().map(|(_, pair)| pair).collect(),
+                    validation.into_iter().map(|(_, pair)| pair).collect(),
+                )
+            })
+            .collect()
+    }
+
     pub fn empty_dimensions(&self) -> Vec<usize> {
         let Some(n_dimensions) = self.data.first().map(|sample| sample.mask().0.len()) else {
             return vec![]
@@ -189,10 +553,20 @@ impl PPCAModel {
     }
 
     pub fn init(state_size: usize, dataset: &Dataset) -> PPCAModel {
+        PPCAModel::init_with_rng(state_size, dataset, &mut rand::thread_rng())
+    }
+
+    /// Like `PPCAModel::init`, but drawing the random transform from the supplied random
+    /// number generator, for reproducible initializations.
+    pub fn init_with_rng<R: Rng + ?Sized>(
+        state_size: usize,
+        dataset: &Dataset,
+        rng: &mut R,
+    ) -> PPCAModel {
         assert!(!dataset.is_empty());
         let output_size = dataset.output_size().expect("dataset is not empty");
         let empty_dimensions = dataset.empty_dimensions();
-        let mut rand_transform = standard_noise_matrix(output_size, state_size);
+        let mut rand_transform = standard_noise_matrix_with_rng(output_size, state_size, rng);
 
         for (dimension, mut row) in rand_transform.row_iter_mut().enumerate() {
             if empty_dimensions.contains(&dimension) {
@@ -209,6 +583,122 @@ impl PPCAModel {
         }
     }
 
+    /// Creates the maximum likelihood model for data with the given mean and covariance,
+    /// in closed form (Tipping & Bishop, 1999). The transform spans the `state_size`
+    /// leading eigenvectors `U` of the covariance, `W = U (Λ - σ²I)^½`, and the noise
+    /// variance `σ²` is the average of the remaining eigenvalues.
+    pub fn from_moments(
+        mean: DVector<f64>,
+        covariance: &DMatrix<f64>,
+        state_size: usize,
+    ) -> PPCAModel {
+        let output_size = mean.len();
+        assert_eq!(
+            covariance.shape(),
+            (output_size, output_size),
+            "covariance has wrong shape"
+        );
+        assert!(
+            state_size < output_size,
+            "state size must be smaller than the output size"
+        );
+
+        let eigen = covariance.clone().symmetric_eigen();
+        let mut order = (0..output_size).collect::<Vec<_>>();
+        order.sort_by(|&a, &b| eigen.eigenvalues[b].total_cmp(&eigen.eigenvalues[a]));
+
+        let noise_variance = (order[state_size..]
+            .iter()
+            .map(|&idx| eigen.eigenvalues[idx])
+            .sum::<f64>()
+            / (output_size - state_size) as f64)
+            .max(f64::EPSILON);
+        let mut transform = DMatrix::zeros(output_size, state_size);
+        for (column, &idx) in order.iter().take(state_size).enumerate() {
+            let scale = (eigen.eigenvalues[idx] - noise_variance).max(0.0).sqrt();
+            transform.set_column(column, &(scale * eigen.eigenvectors.column(idx)));
+        }
+
+        PPCAModel::new(noise_variance.sqrt(), transform, mean)
+    }
+
+    /// Selects the state size by `n_folds`-fold cross-validation: for each candidate, fits
+    /// a model with `PPCAModel::fit` on each training fold, from a random initialization
+    /// derived from `seed`, and returns the held-out log-likelihood per sample, averaged
+    /// over all folds. The best candidate is the one with the largest value.
+    pub fn cross_validate_state_size(
+        dataset: &Dataset,
+        candidate_sizes: &[usize],
+        n_folds: usize,
+        seed: u64,
+    ) -> Vec<(usize, f64)> {
+        let folds = dataset.folds(n_folds);
+        let mut rng = StdRng::seed_from_u64(seed);
+        let runs = candidate_sizes
+            .iter()
+            .flat_map(|&state_size| (0..n_folds).map(move |fold| (state_size, fold)))
+            .map(|(state_size, fold)| (state_size, fold, rng.gen()))
+            .collect::<Vec<(usize, usize, u64)>>();
+        let config = PPCAFitConfig::default();
+
+        let held_out_llks = runs
+            .into_par_iter()
+            .map(|(state_size, fold, run_seed)| {
+                let (training, validation) = &folds[fold];
+                let init = PPCAModel::init_with_rng(
+                    state_size,
+                    training,
+                    &mut StdRng::seed_from_u64(run_seed),
+                );
+                let (model, _) = init.fit(training, &config);
+                model.llk(validation)
+            })
+            .collect::<Vec<_>>();
+
+        let total_weight = dataset.weights.iter().sum::<f64>();
+        candidate_sizes
+            .iter()
+            .zip(held_out_llks.chunks(n_folds))
+            .map(|(&state_size, llks)| (state_size, llks.iter().sum::<f64>() / total_weight))
+            .collect()
+    }
+
+    /// Draws a random ground-truth model, with transform and mean entries from a standard
+    /// normal scaled by `mean_scale` for the mean, and isotropic noise `noise`.
+    pub(crate) fn random_with_rng<R: Rng + ?Sized>(
+        output_size: usize,
+        state_size: usize,
+        noise: f64,
+        mean_scale: f64,
+        rng: &mut R,
+    ) -> PPCAModel {
+        PPCAModel::new(
+            noise,
+            standard_noise_matrix_with_rng(output_size, state_size, rng),
+            mean_scale * standard_noise_with_rng(output_size, rng),
+        )
+    }
+
+    /// Creates a random ground-truth model with isotropic noise `noise` and samples
+    /// `n_samples` fully observed points from it. The transform and the mean have
+    /// standard normal entries. Everything is derived deterministically from `seed`, so
+    /// this is handy to test that fitting recovers the true model.
+    pub fn generate_synthetic(
+        output_size: usize,
+        state_size: usize,
+        n_samples: usize,
+        noise: f64,
+        seed: u64,
+    ) -> (PPCAModel, Dataset) {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let model = PPCAModel::random_with_rng(output_size, state_size, noise, 1.0, &mut rng);
+        let dataset = (0..n_samples)
+            .map(|_| model.sample_one_with_rng(0.0, &mut rng))
+            .collect();
+
+        (model, dataset)
+    }
+
     pub(crate) fn output_covariance(&self) -> &OutputCovariance<'static> {
         &self.output_covariance
     }
@@ -217,6 +707,207 @@ impl PPCAModel {
         &self.mean
     }
 
+    /// The center of mass `μ` of the distribution in the output space.
+    pub fn output_mean(&self) -> &DVector<f64> {
+        &self.mean
+    }
+
+    /// The marginal mean `E[x] = μ` of the output, with nothing observed. This is not to
+    /// be confused with the posterior mean given some observed values, as given by
+    /// `PPCAModel::extrapolate`.
+    pub fn expected_observation(&self) -> DVector<f64> {
+        self.mean.clone()
+    }
+
+    /// The variance `σ²` of the isotropic noise in the output space. Note that
+    /// `PPCAModel::new` takes the standard deviation `σ` instead.
+    pub fn noise_variance(&self) -> f64 {
+        self.output_covariance.isotropic_noise.powi(2)
+    }
+
+    /// The standard deviation `σ` of the isotropic noise.
+    pub fn noise_std(&self) -> f64 {
+        self.output_covariance.isotropic_noise.abs()
+    }
+
+    /// The log of the noise variance, `2 ln σ`, computed without squaring `σ`.
+    pub fn log_noise_variance(&self) -> f64 {
+        2.0 * self.noise_std().ln()
+    }
+
+    /// The linear transformation `W` from the hidden state space to the output space.
+    pub fn loadings(&self) -> &DMatrix<f64> {
+        &self.output_covariance.transform
+    }
+
+    pub fn set_mean(&mut self, mean: DVector<f64>) {
+        assert_eq!(mean.len(), self.output_size(), "mean has wrong output size");
+        self.mean = mean;
+    }
+
+    pub fn set_noise_variance(&mut self, noise_variance: f64) {
+        assert!(
+            noise_variance > 0.0 && noise_variance.is_finite(),
+            "noise variance must be positive and finite"
+        );
+        self.output_covariance.isotropic_noise = noise_variance.sqrt();
+    }
+
+    /// This model with the isotropic noise standard deviation `σ`, the value
+    /// `PPCAModel::new` takes, rather than the variance `σ²` of
+    /// `PPCAModel::set_noise_variance`. Unlike the other setters, this returns a new model
+    /// and leaves this one untouched. Panics unless `σ` is positive and finite, the same
+    /// condition `PPCAModel::validate` checks.
+    #[must_use]
+    pub fn set_noise_std(&self, noise_std: f64) -> PPCAModel {
+        assert!(
+            noise_std > 0.0 && noise_std.is_finite(),
+            "noise standard deviation must be positive and finite"
+        );
+        PPCAModel {
+            output_covariance: OutputCovariance::new_owned(
+                noise_std,
+                self.output_covariance.transform.clone().into_owned(),
+            ),
+            mean: self.mean.clone(),
+        }
+    }
+
+    pub fn set_loadings(&mut self, loadings: DMatrix<f64>) {
+        assert_eq!(
+            loadings.nrows(),
+            self.output_size(),
+            "loadings have wrong output size"
+        );
+        self.output_covariance.transform = Cow::Owned(loadings);
+    }
+
+    /// Flattens the parameters of this model into a single vector, for use with
+    /// gradient-based optimizers. The layout is the transform `W` in column-major order,
+    /// followed by the mean `μ` and, last, `log σ²`. Using the log-variance keeps the
+    /// noise positive whatever the optimizer does.
+    pub fn param_vector(&self) -> DVector<f64> {
+        self.output_covariance
+            .transform
+            .iter()
+            .chain(&self.mean)
+            .copied()
+            .chain([self.noise_variance().ln()])
+            .collect::<Vec<_>>()
+            .into()
+    }
+
+    /// Reconstructs a model from the flat vector produced by `PPCAModel::param_vector`.
+    pub fn from_param_vector(
+        output_size: usize,
+        state_size: usize,
+        params: &DVector<f64>,
+    ) -> PPCAModel {
+        let transform_size = output_size * state_size;
+        assert_eq!(
+            params.len(),
+            transform_size + output_size + 1,
+            "parameter vector has wrong size"
+        );
+        let params = params.as_slice();
+
+        PPCAModel::new(
+            (params[transform_size + output_size] / 2.0).exp(),
+            DMatrix::from_column_slice(output_size, state_size, &params[..transform_size]),
+            DVector::from_column_slice(&params[transform_size..transform_size + output_size]),
+        )
+    }
+
+    /// Checks that this model is well-formed: the transform and the mean have the same
+    /// output size, the noise variance is positive and all parameters are finite. The
+    /// state size is the number of columns of the transform by construction. Use this on
+    /// models coming from untrusted sources.
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        let transform = &*self.output_covariance.transform;
+        if transform.nrows() != self.mean.len() {
+            return Err(ValidationError::OutputSizeMismatch {
+                transform_rows: transform.nrows(),
+                mean_len: self.mean.len(),
+            });
+        }
+
+        let noise = self.output_covariance.isotropic_noise;
+        if noise.is_infinite() {
+            return Err(ValidationError::NonFinite("noise"));
+        }
+        if noise.is_nan() || noise <= 0.0 {
+            return Err(ValidationError::NonPositiveNoise(noise));
+        }
+        if !transform.iter().all(|value| value.is_finite()) {
+            return Err(ValidationError::NonFinite("transform"));
+        }
+        if !self.mean.iter().all(|value| value.is_finite()) {
+            return Err(ValidationError::NonFinite("mean"));
+        }
+
+        Ok(())
+    }
+
+    /// Reads a model encoded as JSON, without loading the whole encoding in memory
+    /// first. Wrap files in a `BufReader` for good performance.
+    pub fn from_json_reader<R: Read>(reader: R) -> Result<PPCAModel, serde_json::Error> {
+        serde_json::from_reader(reader)
+    }
+
+    /// Writes this model encoded as JSON, as read by `PPCAModel::from_json_reader`. Wrap
+    /// files in a `BufWriter` for good performance. The writer is flushed before
+    /// returning, so that write errors are reported rather than lost on drop.
+    pub fn to_json_writer<W: Write>(&self, mut writer: W) -> Result<(), serde_json::Error> {
+        serde_json::to_writer(&mut writer, self)?;
+        writer.flush().map_err(serde_json::Error::io)
+    }
+
+    /// Reads a model from three headerless CSV files: the loading matrix `W`, one row
+    /// per output dimension, the mean `μ`, as a single row or column, and the noise
+    /// variance `σ²`, as a single value. This is the layout written by `numpy.savetxt`
+    /// with `delimiter=","`, e.g., for models fitted with other libraries.
+    pub fn from_csv(
+        loadings_path: &Path,
+        mean_path: &Path,
+        noise_path: &Path,
+    ) -> Result<PPCAModel, CsvError> {
+        let transform = read_csv_matrix(loadings_path)?;
+        let mean = read_csv_vector(mean_path, "mean")?;
+        let noise_variance = read_csv_scalar(noise_path, "noise variance")?;
+
+        // Checks the sign before taking the square root, which would hide it.
+        if noise_variance.is_nan() || noise_variance <= 0.0 {
+            return Err(ValidationError::NonPositiveNoise(noise_variance).into());
+        }
+
+        let model = PPCAModel::new(noise_variance.sqrt(), transform, mean);
+        model.validate()?;
+
+        Ok(model)
+    }
+
+    /// Writes this model to three headerless CSV files, as read by `PPCAModel::from_csv`:
+    /// the loading matrix `W`, one row per output dimension, the mean `μ`, as a single
+    /// column, and the noise variance `σ²`, as a single value.
+    pub fn to_csv(
+        &self,
+        loadings_path: &Path,
+        mean_path: &Path,
+        noise_path: &Path,
+    ) -> Result<(), CsvError> {
+        write_csv_matrix(loadings_path, self.loadings())?;
+        write_csv_matrix(
+            mean_path,
+            &DMatrix::from_column_slice(self.mean.len(), 1, self.mean.as_slice()),
+        )?;
+        write_csv_matrix(
+            noise_path,
+            &DMatrix::from_element(1, 1, self.noise_variance()),
+        )?;
+
+        Ok(())
+    }
+
     pub fn output_size(&self) -> usize {
         self.output_covariance.output_size()
     }
@@ -232,8 +923,25 @@ impl PPCAModel {
         }
     }
 
+    /// The number of free parameters of this model, used for information criteria (e.g.,
+    /// BIC). This is `output_size * state_size` (the transform) `+ output_size` (the
+    /// mean) `+ 1` (the isotropic noise) `- state_size * (state_size - 1) / 2`. The last
+    /// term accounts for the rotational invariance of the model: since the hidden state
+    /// has a fixed standard normal prior, `W` and `W R` give the same distribution for
+    /// any orthogonal `R`, and the `state_size * (state_size - 1) / 2` degrees of freedom
+    /// of `R` are not identifiable from data.
     pub fn n_parameters(&self) -> usize {
-        1 + self.state_size() * self.output_size() + self.mean.nrows()
+        let state_size = self.state_size();
+        (self.output_size() * state_size + self.output_size() + 1)
+            .saturating_sub(state_size * state_size.saturating_sub(1) / 2)
+    }
+
+    /// The degrees of freedom of the reconstruction error of a sample, for calibrating
+    /// chi-squared tests. This is the number of observed dimensions minus the state size
+    /// (the number of constraints absorbed by the hidden state), clamped to at least one.
+    pub fn degrees_of_freedom_observation(&self, sample: &MaskedSample) -> usize {
+        let n_observed = sample.mask.0.iter().filter(|&selected| selected).count();
+        n_observed.saturating_sub(self.state_size()).max(1)
     }
 
     pub fn singular_values(&self) -> DVector<f64> {
@@ -245,6 +953,126 @@ impl PPCAModel {
             .into()
     }
 
+    /// The differential entropy of the output distribution of this model, in nats,
+    /// `(d (1 + ln 2π) + ln det C) / 2` with `C = W Wᵀ + σ²I`.
+    pub fn entropy(&self) -> f64 {
+        self.output_size() as f64 / 2.0 + self.log_normalizer()
+    }
+
+    /// How much of the variance of each output dimension is explained by the hidden
+    /// state rather than by the noise, `|w_j|² / (|w_j|² + σ²)`, where `w_j` is the
+    /// corresponding row of the transform. This is between 0 (pure noise) and 1.
+    pub fn dimension_importance(&self) -> DVector<f64> {
+        let noise_variance = self.noise_variance();
+        self.output_covariance
+            .transform
+            .row_iter()
+            .map(|row| {
+                let signal = row.norm_squared();
+                signal / (signal + noise_variance)
+            })
+            .collect::<Vec<_>>()
+            .into()
+    }
+
+    /// The indices of the `k` output dimensions with the largest
+    /// `PPCAModel::dimension_importance`, from the most to the least important.
+    pub fn top_k_dimensions(&self, k: usize) -> Vec<usize> {
+        let importance = self.dimension_importance();
+        let mut order = (0..self.output_size()).collect::<Vec<_>>();
+        order.sort_by(|&a, &b| importance[b].total_cmp(&importance[a]));
+        order.truncate(k);
+        order
+    }
+
+    /// The Kullback-Leibler divergence `KL(self || other)` between the output
+    /// distributions of two models with the same output size,
+    /// `(tr(C₂⁻¹ C₁) + (μ₂ - μ₁)ᵀ C₂⁻¹ (μ₂ - μ₁) - d + log det C₂ - log det C₁) / 2`,
+    /// computed without building the full covariances.
+    pub fn kl_divergence(&self, other: &PPCAModel) -> f64 {
+        assert_eq!(
+            self.output_size(),
+            other.output_size(),
+            "models have different output sizes"
+        );
+        let transform = &*self.output_covariance.transform;
+        let other_transform = &*other.output_covariance.transform;
+        let other_inner_inverse = other.output_covariance.inner_inverse();
+        let other_noise_variance = other.noise_variance();
+
+        // tr(C₂⁻¹ C₁) through the Woodbury identity for C₂⁻¹.
+        let cross = other_transform.transpose() * transform;
+        let trace_transform = (transform.norm_squared()
+            - (&other_inner_inverse * &cross).dot(&cross))
+            / other_noise_variance;
+        let trace_noise = self.noise_variance()
+            * (self.output_size() as f64
+                - (&other_inner_inverse * other.output_covariance.inner_product()).trace())
+            / other_noise_variance;
+
+        let mahalanobis = other
+            .output_covariance
+            .quadratic_form(&(&other.mean - &self.mean));
+
+        (trace_transform + trace_noise + mahalanobis - self.output_size() as f64
+            + other.output_covariance.covariance_log_det()
+            - self.output_covariance.covariance_log_det())
+            / 2.0
+    }
+
+    /// The mutual information between each output dimension and the hidden state, i.e.,
+    /// the expected reduction in the entropy of the posterior of the hidden state when
+    /// observing that dimension alone: `log(1 + |w_j|² / σ²) / 2`, where `w_j` is the
+    /// corresponding row of the transform.
+    pub fn information_gain_per_feature(&self) -> DVector<f64> {
+        let noise_variance = self.noise_variance();
+        self.output_covariance
+            .transform
+            .row_iter()
+            .map(|row| (row.norm_squared() / noise_variance).ln_1p() / 2.0)
+            .collect::<Vec<_>>()
+            .into()
+    }
+
+    /// The log-determinant of the output covariance, computed with the matrix
+    /// determinant lemma `log det(W Wᵀ + σ²I) = (d - k) log σ² + log det(σ²I + WᵀW)`,
+    /// so that only the `k × k` inner matrix is ever built. This is cheap enough, at
+    /// `O(d k²)`, not to be cached.
+    pub fn logdet_covariance(&self) -> f64 {
+        self.output_covariance.covariance_log_det()
+    }
+
+    /// The log of the normalizing constant of the output distribution,
+    /// `(d log 2π + log det(W Wᵀ + σ²I)) / 2`, where `d` is the output size. This is
+    /// minus the log-density at the mean.
+    pub fn log_normalizer(&self) -> f64 {
+        (self.output_size() as f64 * LN_2PI + self.logdet_covariance()) / 2.0
+    }
+
+    /// How far the covariance `W Wᵀ + σ²I` implied by this model is from the sample
+    /// covariance `C` of the dataset, as the relative Frobenius norm
+    /// `‖C - W Wᵀ - σ²I‖ / ‖C‖`. For large output sizes, both covariances are compared
+    /// after a random projection onto a lower dimensional subspace, where missing values
+    /// are filled with the sample mean.
+    pub fn covariance_distance(&self, dataset: &Dataset) -> f64 {
+        let (sample_covariance, model_covariance) =
+            if self.output_size() <= COVARIANCE_DISTANCE_MAX_DIMENSION {
+                (dataset.sample_covariance(), self.output_covariance.matrix())
+            } else {
+                let projection = standard_noise_matrix_with_rng(
+                    self.output_size(),
+                    COVARIANCE_DISTANCE_MAX_DIMENSION,
+                    &mut StdRng::seed_from_u64(COVARIANCE_DISTANCE_SEED),
+                ) / (COVARIANCE_DISTANCE_MAX_DIMENSION as f64).sqrt();
+                (
+                    dataset.projected_sample_covariance(&projection),
+                    self.output_covariance.projected_matrix(&projection),
+                )
+            };
+
+        (&sample_covariance - model_covariance).norm() / sample_covariance.norm()
+    }
+
     pub(crate) fn llk_one(&self, sample: &MaskedSample) -> f64 {
         let sample = if !sample.is_empty() {
             sample
@@ -280,25 +1108,322 @@ impl PPCAModel {
             .into()
     }
 
-    pub(crate) fn sample_one(&self, mask_prob: f64) -> MaskedSample {
-        let sampled_state: DVector<f64> =
-            &*self.output_covariance.transform * standard_noise(self.state_size()) + &self.mean;
-        let noise: DVector<f64> =
-            self.output_covariance.isotropic_noise * standard_noise(self.output_size());
-        let mask = Mask(
-            Bernoulli::new(1.0 - mask_prob as f64)
-                .expect("invalid mask probability")
-                .sample_iter(rand::thread_rng())
-                .take(self.output_size())
-                .collect::<BitVec>(),
+    /// The log-likelihood of each sample under the lower-rank model keeping only the
+    /// columns of the transform in `active_latent_dims`, i.e., with the other hidden
+    /// dimensions ignored.
+    pub fn marginal_llk_latent_subset(
+        &self,
+        dataset: &Dataset,
+        active_latent_dims: &[usize],
+    ) -> DVector<f64> {
+        assert!(
+            active_latent_dims
+                .iter()
+                .all(|&idx| idx < self.state_size()),
+            "latent dimension out of range"
         );
+        let transform = self
+            .output_covariance
+            .transform
+            .select_columns(active_latent_dims);
+        PPCAModel::new(
+            self.output_covariance.isotropic_noise,
+            transform,
+            self.mean.clone(),
+        )
+        .llks(dataset)
+    }
 
-        MaskedSample {
+    /// Checks that `dataset` is well formed (see `Dataset::validate`) and that its
+    /// samples have the output size of this model. This is what the `try_` variants of
+    /// the dataset methods check before delegating to the panicking versions.
+    pub(crate) fn check_dataset(&self, dataset: &Dataset) -> Result<(), DatasetError> {
+        dataset.validate()?;
+        dataset.validate_dimension(self.output_size())
+    }
+
+    /// Like `PPCAModel::llk`, but returns an error instead of panicking when the dataset
+    /// is malformed or its samples do not have the output size of this model.
+    pub fn try_llk(&self, dataset: &Dataset) -> Result<f64, DatasetError> {
+        self.check_dataset(dataset)?;
+        Ok(self.llk(dataset))
+    }
+
+    /// Like `PPCAModel::llks`, but returns an error instead of panicking when the dataset
+    /// is malformed (see `Dataset::validate`) or its samples do not have the output size
+    /// of this model.
+    pub fn try_llks(&self, dataset: &Dataset) -> Result<DVector<f64>, DatasetError> {
+        self.check_dataset(dataset)?;
+        Ok(self.llks(dataset))
+    }
+
+    /// The log-likelihood of a single sample, without having to build a `Dataset`
+    /// around it. Equal to the corresponding entry of `PPCAModel::llks`.
+    pub fn llk_single(&self, sample: &MaskedSample) -> f64 {
+        self.llk_one(sample)
+    }
+
+    /// The log-likelihood `log p(x_o)` of the observed dimensions `x_o` of a single
+    /// sample, with the unobserved dimensions marginalized out. This is exact, since the
+    /// marginal of a Gaussian is Gaussian. Useful for scoring observations that are
+    /// revealed one dimension at a time.
+    pub fn conditional_llk(&self, sample: &MaskedSample) -> f64 {
+        self.llk_one(sample)
+    }
+
+    /// The gradient of the log-likelihood of a single sample with respect to
+    /// `PPCAModel::param_vector`. With `r = x - μ` and `C = W Wᵀ + σ²I` restricted to the
+    /// observed dimensions, the gradient is `C⁻¹ r` for `μ`, `C⁻¹ r rᵀ C⁻¹ W - C⁻¹ W` for
+    /// `W` and `σ² (|C⁻¹ r|² - tr C⁻¹) / 2` for `log σ²`. Everything is calculated in the
+    /// state space through the Woodbury identity, using `C⁻¹ W = W M⁻¹` with the
+    /// `inner_matrix` `M = Wᵀ W + σ²I`.
+    pub(crate) fn llk_gradient_one(&self, sample: &MaskedSample) -> DVector<f64> {
+        if sample.is_empty() {
+            return DVector::zeros(self.output_size() * self.state_size() + self.output_size() + 1);
+        }
+
+        let sub_sample = sample.mask.mask(&(sample.data_vector() - &self.mean));
+        let sub_covariance = self.output_covariance.masked(&sample.mask);
+        let sub_transform = &*sub_covariance.transform;
+        let inner_inverse = sub_covariance.inner_inverse();
+        let noise_variance = self.noise_variance();
+
+        // This is `C⁻¹ r`.
+        let precision_residual = (&sub_sample
+            - sub_transform * (&inner_inverse * (sub_transform.transpose() * &sub_sample)))
+            / noise_variance;
+        let transform_gradient = &precision_residual
+            * (precision_residual.transpose() * sub_transform)
+            - sub_transform * &inner_inverse;
+        // Using `tr C⁻¹ = (n_observed - state_size + σ² tr M⁻¹) / σ²`.
+        let log_noise_gradient = (noise_variance
+            * (precision_residual.norm_squared() - inner_inverse.trace())
+            - (sub_covariance.output_size() as f64 - self.state_size() as f64))
+            / 2.0;
+
+        sample
+            .mask
+            .expand_rows(&transform_gradient)
+            .iter()
+            .chain(&sample.mask.expand(&precision_residual))
+            .copied()
+            .chain([log_noise_gradient])
+            .collect::<Vec<_>>()
+            .into()
+    }
+
+    /// Calculates the negative log-likelihood of a dataset and its gradient with
+    /// respect to `PPCAModel::param_vector`, for use with external optimizers.
+    pub fn neg_llk_and_gradient(&self, dataset: &Dataset) -> (f64, DVector<f64>) {
+        let n_params = self.output_size() * self.state_size() + self.output_size() + 1;
+        let (llk, gradient) = dataset
+            .data
+            .par_iter()
+            .zip(&dataset.weights)
+            .map(|(sample, &weight)| {
+                (
+                    weight * self.llk_one(sample),
+                    weight * self.llk_gradient_one(sample),
+                )
+            })
+            .reduce(
+                || (0.0, DVector::zeros(n_params)),
+                |(llk, gradient), (llk_, gradient_)| (llk + llk_, gradient + gradient_),
+            );
+
+        (-llk, -gradient)
+    }
+
+    /// Calculates the score, i.e., the gradient of the log-likelihood with respect to
+    /// `PPCAModel::param_vector`, of each sample in the dataset. Each row of the returned
+    /// matrix corresponds to a sample. Sample weights are not applied.
+    pub fn score_samples(&self, dataset: &Dataset) -> DMatrix<f64> {
+        let scores = dataset
+            .data
+            .par_iter()
+            .map(|sample| self.llk_gradient_one(sample).transpose())
+            .collect::<Vec<_>>();
+
+        if scores.is_empty() {
+            DMatrix::zeros(
+                0,
+                self.output_size() * self.state_size() + self.output_size() + 1,
+            )
+        } else {
+            DMatrix::from_rows(&scores)
+        }
+    }
+
+    /// Calculates the Hessian of the negative log-likelihood of a dataset with respect to
+    /// `PPCAModel::param_vector` by central finite differences of
+    /// `PPCAModel::neg_llk_and_gradient`, with step size `epsilon`. The result is
+    /// symmetrized. This is meant as a diagnostic tool (e.g., for standard errors) and
+    /// is not fast: it evaluates the gradient twice per parameter.
+    pub fn hessian_neg_llk(&self, dataset: &Dataset, epsilon: f64) -> DMatrix<f64> {
+        assert!(epsilon > 0.0, "finite difference step must be positive");
+        let params = self.param_vector();
+        let gradient_at = |params: DVector<f64>| {
+            PPCAModel::from_param_vector(self.output_size(), self.state_size(), &params)
+                .neg_llk_and_gradient(dataset)
+                .1
+        };
+
+        let mut hessian = DMatrix::zeros(params.len(), params.len());
+        for (idx, mut column) in hessian.column_iter_mut().enumerate() {
+            let mut plus = params.clone();
+            plus[idx] += epsilon;
+            let mut minus = params.clone();
+            minus[idx] -= epsilon;
+            column.copy_from(&((gradient_at(plus) - gradient_at(minus)) / (2.0 * epsilon)));
+        }
+
+        (&hessian + hessian.transpose()) / 2.0
+    }
+
+    /// Compares the gradient of `PPCAModel::neg_llk_and_gradient` with central finite
+    /// differences of the negative log-likelihood, with step size `epsilon`, and returns
+    /// the largest error over the parameters, relative to the magnitude of the gradient
+    /// entry (or absolute, for entries smaller than 1, which finite differences cannot
+    /// resolve relatively). Errors below `1e-5` indicate a correct gradient. This is a
+    /// development tool, e.g., when extending the model, and is not fast.
+    pub fn check_gradient(&self, dataset: &Dataset, epsilon: f64) -> f64 {
+        assert!(epsilon > 0.0, "finite difference step must be positive");
+        let params = self.param_vector();
+        let (_, gradient) = self.neg_llk_and_gradient(dataset);
+        let neg_llk_at = |params: DVector<f64>| {
+            -PPCAModel::from_param_vector(self.output_size(), self.state_size(), &params)
+                .llk(dataset)
+        };
+
+        (0..params.len())
+            .map(|idx| {
+                let mut plus = params.clone();
+                plus[idx] += epsilon;
+                let mut minus = params.clone();
+                minus[idx] -= epsilon;
+                let numerical = (neg_llk_at(plus) - neg_llk_at(minus)) / (2.0 * epsilon);
+                let scale = gradient[idx].abs().max(numerical.abs()).max(1.0);
+                (gradient[idx] - numerical).abs() / scale
+            })
+            .fold(0.0, f64::max)
+    }
+
+    /// One step of gradient ascent on the log-likelihood of the dataset, taken in the
+    /// parameter space of `PPCAModel::param_vector`.
+    #[must_use]
+    pub fn update_from_gradient(&self, dataset: &Dataset, learning_rate: f64) -> PPCAModel {
+        let (_, neg_gradient) = self.neg_llk_and_gradient(dataset);
+        PPCAModel::from_param_vector(
+            self.output_size(),
+            self.state_size(),
+            &(self.param_vector() - learning_rate * neg_gradient),
+        )
+    }
+
+    /// One step of Adam ascent on the log-likelihood of the dataset, taken in the
+    /// parameter space of `PPCAModel::param_vector`. The moment estimates `m` and `v`
+    /// are updated in place and should start as zeros of the size of the parameter
+    /// vector; `t` is the number of the step, starting at 1.
+    #[allow(clippy::too_many_arguments)]
+    #[must_use]
+    pub fn update_from_gradient_adam(
+        &self,
+        dataset: &Dataset,
+        lr: f64,
+        beta1: f64,
+        beta2: f64,
+        eps: f64,
+        m: &mut DVector<f64>,
+        v: &mut DVector<f64>,
+        t: usize,
+    ) -> PPCAModel {
+        assert!(t > 0, "Adam steps are numbered from 1");
+        let (_, neg_gradient) = self.neg_llk_and_gradient(dataset);
+        let gradient = -neg_gradient;
+        assert_eq!(m.len(), gradient.len(), "first moment has wrong size");
+        assert_eq!(v.len(), gradient.len(), "second moment has wrong size");
+
+        *m = beta1 * &*m + (1.0 - beta1) * &gradient;
+        *v = beta2 * &*v + (1.0 - beta2) * gradient.map(|g| g.powi(2));
+        let m_hat = &*m / (1.0 - beta1.powi(t as i32));
+        let v_hat = &*v / (1.0 - beta2.powi(t as i32));
+        let step = m_hat.zip_map(&v_hat, |m, v| lr * m / (v.sqrt() + eps));
+
+        PPCAModel::from_param_vector(
+            self.output_size(),
+            self.state_size(),
+            &(self.param_vector() + step),
+        )
+    }
+
+    pub(crate) fn sample_one(&self, mask_prob: f64) -> MaskedSample {
+        self.sample_one_with_rng(mask_prob, &mut rand::thread_rng())
+    }
+
+    pub(crate) fn sample_one_with_rng<R: Rng + ?Sized>(
+        &self,
+        mask_prob: f64,
+        rng: &mut R,
+    ) -> MaskedSample {
+        let sampled_state: DVector<f64> = &*self.output_covariance.transform
+            * standard_noise_with_rng(self.state_size(), rng)
+            + &self.mean;
+        let noise: DVector<f64> = self.output_covariance.isotropic_noise
+            * standard_noise_with_rng(self.output_size(), rng);
+        let mask = Mask(
+            Bernoulli::new(1.0 - mask_prob as f64)
+                .expect("invalid mask probability")
+                .sample_iter(rng)
+                .take(self.output_size())
+                .collect::<BitVec>(),
+        );
+
+        MaskedSample {
             data: mask.fillna(&(sampled_state + noise)),
             mask,
         }
     }
 
+    /// Like `PPCAModel::sample`, but reproducible: the samples are derived
+    /// deterministically from `seed`.
+    pub fn sample_with_seed(&self, dataset_size: usize, mask_prob: f64, seed: u64) -> Dataset {
+        let mut rng = StdRng::seed_from_u64(seed);
+        (0..dataset_size)
+            .map(|_| self.sample_one_with_rng(mask_prob, &mut rng))
+            .collect()
+    }
+
+    /// Draws fully observed samples directly from the output distribution
+    /// `N(μ, W Wᵀ + σ²I)`, using the Cholesky factor of the full covariance instead of
+    /// drawing the hidden state first. The samples are derived deterministically from
+    /// `seed`.
+    pub fn sample_prior(&self, n_samples: usize, seed: u64) -> Dataset {
+        let factor = self
+            .output_covariance
+            .matrix()
+            .cholesky()
+            .expect("output covariance is positive definite")
+            .unpack();
+        let mut rng = StdRng::seed_from_u64(seed);
+
+        (0..n_samples)
+            .map(|_| {
+                MaskedSample::unmasked(
+                    &factor * standard_noise_with_rng(self.output_size(), &mut rng) + &self.mean,
+                )
+            })
+            .collect()
+    }
+
+    /// Draws a single fully observed sample from the model. With a seed, the sample is
+    /// reproducible; otherwise, the thread random generator is used.
+    pub fn sample_one_unmasked(&self, seed: Option<u64>) -> MaskedSample {
+        match seed {
+            Some(seed) => self.sample_one_with_rng(0.0, &mut StdRng::seed_from_u64(seed)),
+            None => self.sample_one(0.0),
+        }
+    }
+
     pub fn sample(&self, dataset_size: usize, mask_prob: f64) -> Dataset {
         (0..dataset_size)
             .into_par_iter()
@@ -306,6 +1431,46 @@ impl PPCAModel {
             .collect()
     }
 
+    /// Draws a fully observed sample from the conditional distribution given the observed
+    /// values of `sample`: observed dimensions are kept as they are and missing ones are
+    /// drawn from `p(x_missing | x_observed)`. This is done exactly by drawing the hidden
+    /// state from its posterior and then the output from the model. The randomness is
+    /// derived deterministically from `seed`.
+    pub fn sample_conditional(&self, sample: &MaskedSample, seed: u64) -> MaskedSample {
+        self.sample_conditional_with_rng(sample, &mut StdRng::seed_from_u64(seed))
+    }
+
+    pub(crate) fn sample_conditional_with_rng<R: Rng + ?Sized>(
+        &self,
+        sample: &MaskedSample,
+        rng: &mut R,
+    ) -> MaskedSample {
+        let drawn = self.sample_posterior_output_with_rng(sample, rng);
+        MaskedSample::unmasked(sample.mask.choose(&sample.data_vector(), &drawn))
+    }
+
+    /// Draws the hidden state from its posterior given `sample` and then the whole
+    /// output, observed dimensions included, from the model.
+    pub(crate) fn sample_posterior_output_with_rng<R: Rng + ?Sized>(
+        &self,
+        sample: &MaskedSample,
+        rng: &mut R,
+    ) -> DVector<f64> {
+        let inferred = self.infer_one(sample);
+        let state_noise = inferred
+            .covariance
+            .clone()
+            .cholesky()
+            .expect("posterior covariance is positive definite")
+            .l()
+            * standard_noise_with_rng(self.state_size(), rng);
+        let state = &inferred.state + state_noise;
+        &*self.output_covariance.transform * state
+            + &self.mean
+            + self.output_covariance.isotropic_noise
+                * standard_noise_with_rng(self.output_size(), rng)
+    }
+
     pub(crate) fn infer_one(&self, sample: &MaskedSample) -> InferredMasked {
         if sample.is_empty() {
             return self.uninferred();
@@ -320,6 +1485,13 @@ impl PPCAModel {
         }
     }
 
+    /// The most probable hidden state given the observed values of the sample. Since the
+    /// posterior of the hidden state is Gaussian, this coincides with its mean, the
+    /// state of `PPCAModel::infer`.
+    pub fn posterior_mode(&self, sample: &MaskedSample) -> DVector<f64> {
+        self.infer_one(sample).state
+    }
+
     pub fn infer(&self, dataset: &Dataset) -> Vec<InferredMasked> {
         dataset
             .data
@@ -328,6 +1500,13 @@ impl PPCAModel {
             .collect()
     }
 
+    /// Like `PPCAModel::infer`, but returns an error instead of panicking when the dataset
+    /// is malformed or its samples do not have the output size of this model.
+    pub fn try_infer(&self, dataset: &Dataset) -> Result<Vec<InferredMasked>, DatasetError> {
+        self.check_dataset(dataset)?;
+        Ok(self.infer(dataset))
+    }
+
     pub(crate) fn smooth_one(&self, sample: &MaskedSample) -> MaskedSample {
         MaskedSample::unmasked(self.infer_one(sample).smoothed(&self))
     }
@@ -341,6 +1520,168 @@ impl PPCAModel {
             .collect()
     }
 
+    /// Like `PPCAModel::smooth`, but returns an error instead of panicking when the
+    /// dataset is malformed or its samples do not have the output size of this model.
+    pub fn try_smooth(&self, dataset: &Dataset) -> Result<Dataset, DatasetError> {
+        self.check_dataset(dataset)?;
+        Ok(self.smooth(dataset))
+    }
+
+    /// The posterior mean of the hidden state of each sample, one sample per row.
+    pub fn project(&self, dataset: &Dataset) -> DMatrix<f64> {
+        let states = dataset
+            .data
+            .par_iter()
+            .map(|sample| self.infer_one(sample).state.transpose())
+            .collect::<Vec<_>>();
+
+        if states.is_empty() {
+            DMatrix::zeros(0, self.state_size())
+        } else {
+            DMatrix::from_rows(&states)
+        }
+    }
+
+    /// The indices of the `n` columns of the transform with the largest norms, from the
+    /// largest to the smallest.
+    pub(crate) fn largest_loading_columns(&self, n: usize) -> Vec<usize> {
+        assert!(n <= self.state_size(), "not enough hidden dimensions");
+        let norms = self
+            .output_covariance
+            .transform
+            .column_iter()
+            .map(|column| column.norm())
+            .collect::<Vec<_>>();
+        let mut order = (0..self.state_size()).collect::<Vec<_>>();
+        order.sort_by(|&a, &b| norms[b].total_cmp(&norms[a]));
+        order.truncate(n);
+        order
+    }
+
+    /// Projects each sample to 2D for plotting: the posterior means of the hidden state
+    /// along the two columns of the transform with the largest norms, one sample per
+    /// row. The model needs at least two hidden dimensions.
+    pub fn project_to_2d(&self, dataset: &Dataset) -> DMatrix<f64> {
+        self.project(dataset)
+            .select_columns(&self.largest_loading_columns(2))
+    }
+
+    /// Maps hidden states, one per row, back to fully observed outputs `W z + μ`.
+    pub fn reconstruct(&self, states: &DMatrix<f64>) -> Dataset {
+        assert_eq!(states.ncols(), self.state_size(), "states have wrong size");
+        states
+            .row_iter()
+            .map(|state| {
+                MaskedSample::unmasked(
+                    &*self.output_covariance.transform * state.transpose() + &self.mean,
+                )
+            })
+            .collect()
+    }
+
+    /// Decodes `n_steps` evenly spaced points of the segment between the posterior mean
+    /// states of `from` and `to`, endpoints included.
+    pub fn interpolate(&self, from: &MaskedSample, to: &MaskedSample, n_steps: usize) -> Dataset {
+        assert!(n_steps >= 2, "need at least the two endpoints");
+        let from_state = self.infer_one(from).state;
+        let to_state = self.infer_one(to).state;
+        let states = DMatrix::from_fn(n_steps, self.state_size(), |step, idx| {
+            let t = step as f64 / (n_steps - 1) as f64;
+            (1.0 - t) * from_state[idx] + t * to_state[idx]
+        });
+
+        self.reconstruct(&states)
+    }
+
+    /// Both `PPCAModel::project` and the reconstruction of the projected states, as in
+    /// `PPCAModel::smooth`, in a single pass over the dataset. The reconstructions keep
+    /// the weights of the dataset.
+    pub fn project_and_reconstruct(&self, dataset: &Dataset) -> (DMatrix<f64>, Dataset) {
+        let (states, reconstructed): (Vec<_>, Vec<_>) = dataset
+            .data
+            .par_iter()
+            .map(|sample| {
+                let inferred = self.infer_one(sample);
+                (
+                    inferred.state.transpose(),
+                    MaskedSample::unmasked(inferred.smoothed(self)),
+                )
+            })
+            .unzip();
+
+        let states = if states.is_empty() {
+            DMatrix::zeros(0, self.state_size())
+        } else {
+            DMatrix::from_rows(&states)
+        };
+
+        (
+            states,
+            Dataset::new_with_weights(reconstructed, dataset.weights.clone()),
+        )
+    }
+
+    /// Predicts the values of `target_dims` given the values of `observed_dims`, in the
+    /// same order. Returns the mean and the standard deviation of each target under the
+    /// conditional distribution. Targets which are also observed are predicted exactly,
+    /// with zero standard deviation.
+    pub fn predict_from_partial(
+        &self,
+        observed_dims: &[usize],
+        observed_values: &DVector<f64>,
+        target_dims: &[usize],
+    ) -> (DVector<f64>, DVector<f64>) {
+        let sample = MaskedSample::from_partial(self.output_size(), observed_dims, observed_values);
+        let inferred = self.infer_one(&sample);
+        let mean = inferred.extrapolated(self, &sample);
+        let variance = inferred.extrapolated_covariance_diagonal(self, &sample);
+
+        (
+            mean.select_rows(target_dims),
+            variance.select_rows(target_dims).map(f64::sqrt),
+        )
+    }
+
+    /// The `(1 - alpha)` central prediction interval `mean ± z_{1 - alpha/2} std` of each
+    /// dimension under the conditional distribution given the observed values of
+    /// `sample`, returned as `(lower, upper)`. Observed dimensions get the degenerate
+    /// interval at their value.
+    pub fn predictive_interval(
+        &self,
+        sample: &MaskedSample,
+        alpha: f64,
+    ) -> (DVector<f64>, DVector<f64>) {
+        assert!(alpha > 0.0 && alpha < 1.0, "alpha must be in (0, 1)");
+        let inferred = self.infer_one(sample);
+        let mean = inferred.extrapolated(self, sample);
+        let half_width = inferred
+            .extrapolated_covariance_diagonal(self, sample)
+            .map(f64::sqrt)
+            * standard_normal_quantile(1.0 - alpha / 2.0);
+
+        (&mean - &half_width, mean + half_width)
+    }
+
+    /// For each dimension, the ratio of the variance of its posterior predictive given
+    /// the observed values of `sample` to its prior variance `(W Wᵀ + σ²I)_jj`. Values
+    /// near 0 mean the dimension is well predicted from the observations, values near 1
+    /// that the model is essentially guessing. Observed dimensions have a ratio of 0.
+    pub fn imputation_uncertainty_ratio(&self, sample: &MaskedSample) -> DVector<f64> {
+        let posterior_variance = self
+            .infer_one(sample)
+            .extrapolated_covariance_diagonal(self, sample);
+        let noise_variance = self.noise_variance();
+        let prior_variance = self
+            .output_covariance
+            .transform
+            .row_iter()
+            .map(|row| row.norm_squared() + noise_variance)
+            .collect::<Vec<_>>()
+            .into();
+
+        posterior_variance.component_div(&prior_variance)
+    }
+
     pub(crate) fn extrapolate_one(&self, sample: &MaskedSample) -> MaskedSample {
         MaskedSample::unmasked(self.infer_one(sample).extrapolated(self, sample))
     }
@@ -354,39 +1695,193 @@ impl PPCAModel {
             .collect()
     }
 
+    /// Like `PPCAModel::extrapolate`, but returns an error instead of panicking when the
+    /// dataset is malformed or its samples do not have the output size of this model.
+    pub fn try_extrapolate(&self, dataset: &Dataset) -> Result<Dataset, DatasetError> {
+        self.check_dataset(dataset)?;
+        Ok(self.extrapolate(dataset))
+    }
+
+    /// Performs one EM iteration over `dataset`. An empty dataset carries no information,
+    /// so the model is returned unchanged.
     #[must_use]
     pub fn iterate(&self, dataset: &Dataset) -> PPCAModel {
-        let inferred = self.infer(dataset);
+        self.iterate_with_config(dataset, &PPCAFitConfig::default())
+    }
 
-        // Updated transform:
-        let total_cross_moment = dataset
+    /// The expected complete-data log-likelihood `Q(θ | θ_inference)` that EM maximizes:
+    /// the expectation of `log p(x_observed, z | self)` under the posterior of the hidden
+    /// state given by `inference_model`, summed over the weighted samples. When
+    /// `inference_model` is this model, this is the log-likelihood minus the entropies
+    /// of the posteriors of the hidden states.
+    pub fn expected_complete_data_llk(
+        &self,
+        dataset: &Dataset,
+        inference_model: &PPCAModel,
+    ) -> f64 {
+        assert_eq!(
+            self.state_size(),
+            inference_model.state_size(),
+            "models have different state sizes"
+        );
+        let noise_variance = self.noise_variance();
+
+        dataset
             .data
             .par_iter()
             .zip(&dataset.weights)
-            .zip(&inferred)
-            .map(|((sample, &weight), inferred)| {
-                let centered_filled = sample.mask.fillna(&(sample.data_vector() - &self.mean));
-                weight * centered_filled * inferred.state.transpose()
+            .map(|(sample, &weight)| {
+                let inferred = inference_model.infer_one(sample);
+                let prior_llk =
+                    -(self.state_size() as f64 * LN_2PI + inferred.second_moment().trace()) / 2.0;
+
+                if sample.is_empty() {
+                    return weight * prior_llk;
+                }
+
+                let sub_covariance = self.output_covariance.masked(&sample.mask);
+                let sub_transform = &*sub_covariance.transform;
+                let residual = sample.mask.mask(&(sample.data_vector() - &self.mean))
+                    - sub_transform * &inferred.state;
+                let expected_square_error = residual.norm_squared()
+                    + (sub_transform * &inferred.covariance).dot(sub_transform);
+                let n_observed = sub_covariance.output_size() as f64;
+                let output_llk = -(n_observed * (LN_2PI + noise_variance.ln())
+                    + expected_square_error / noise_variance)
+                    / 2.0;
+
+                weight * (prior_llk + output_llk)
             })
+            .sum()
+    }
+
+    /// Performs one EM iteration, like `PPCAModel::iterate`, and reports how the
+    /// log-likelihood and the parameters changed, to help debugging the optimization.
+    pub fn em_step_diagnostics(&self, dataset: &Dataset) -> (PPCAModel, EMDiagnostics) {
+        let llk_before = self.llk(dataset);
+        let iterated = self.iterate(dataset);
+        let llk_after = iterated.llk(dataset);
+        let params = iterated.param_vector();
+
+        let diagnostics = EMDiagnostics {
+            llk_before,
+            llk_after,
+            delta_llk: llk_after - llk_before,
+            loading_change: (iterated.loadings() - self.loadings()).norm(),
+            noise_change: (iterated.noise_variance() - self.noise_variance()).abs(),
+            mean_change: (iterated.output_mean() - self.output_mean()).norm(),
+            has_nan: llk_after.is_nan() || params.iter().any(|param| param.is_nan()),
+            has_inf: llk_after.is_infinite() || params.iter().any(|param| param.is_infinite()),
+        };
+
+        (iterated, diagnostics)
+    }
+
+    /// Performs one EM iteration honoring the `l2_penalty`, `fix_mean` and `fix_noise`
+    /// settings of `config`. The stopping settings are ignored. An empty dataset leaves
+    /// the model unchanged.
+    #[must_use]
+    pub fn iterate_with_config(&self, dataset: &Dataset, config: &PPCAFitConfig) -> PPCAModel {
+        if dataset.is_empty() {
+            return self.clone();
+        }
+
+        self.m_step_with_config(&self.e_step(dataset), config)
+    }
+
+    /// Zero-initialized sufficient statistics with the dimensions of this model, to be
+    /// filled with `SufficientStatistics::add_sample` or `SufficientStatistics::merge`.
+    pub fn sufficient_statistics_empty(&self) -> SufficientStatistics {
+        SufficientStatistics {
+            cross_moment: DMatrix::zeros(self.output_size(), self.state_size()),
+            second_moments: vec![
+                DMatrix::zeros(self.state_size(), self.state_size());
+                self.output_size()
+            ],
+            square_error: 0.0,
+            deviation_square_sum: 0.0,
+            total_deviation: DVector::zeros(self.output_size()),
+            totals: DVector::zeros(self.output_size()),
+        }
+    }
+
+    /// The E-step of the EM algorithm: accumulates the sufficient statistics of the whole
+    /// dataset under this model.
+    pub fn e_step(&self, dataset: &Dataset) -> SufficientStatistics {
+        dataset
+            .data
+            .par_iter()
+            .zip(&dataset.weights)
+            .fold(
+                || self.sufficient_statistics_empty(),
+                |mut statistics, (sample, &weight)| {
+                    statistics.add_sample(self, sample, weight);
+                    statistics
+                },
+            )
             .reduce(
-                || DMatrix::zeros(self.output_size(), self.state_size()),
-                |this, other| this + other,
-            ); // sum() no work...
+                || self.sufficient_statistics_empty(),
+                |mut statistics, other| {
+                    statistics.merge(&other);
+                    statistics
+                },
+            )
+    }
+
+    /// The complete-data expectations of the E-step: the posterior state means `E[z_i]`,
+    /// one sample per row, the weighted sum of the posterior second moments
+    /// `E[z_i z_iᵀ]` and the total weight. Empty samples carry no information and are
+    /// left out of the sum and the total weight. These are the quantities the M-step is
+    /// built upon, exposed for implementing custom M-steps.
+    pub fn complete_data_sufficient_statistics(
+        &self,
+        dataset: &Dataset,
+    ) -> (DMatrix<f64>, DMatrix<f64>, f64) {
+        let inferred = self.infer(dataset);
+        let mut states = DMatrix::zeros(dataset.len(), self.state_size());
+        for (mut row, inferred) in states.row_iter_mut().zip(&inferred) {
+            row.copy_from(&inferred.state.transpose());
+        }
+
+        let (second_moment, total_weight) = dataset
+            .data
+            .iter()
+            .zip(&dataset.weights)
+            .zip(&inferred)
+            .filter(|((sample, _), _)| !sample.is_empty())
+            .fold(
+                (DMatrix::zeros(self.state_size(), self.state_size()), 0.0),
+                |(second_moment, total_weight), ((_, &weight), inferred)| {
+                    (
+                        second_moment + weight * inferred.second_moment(),
+                        total_weight + weight,
+                    )
+                },
+            );
+
+        (states, second_moment, total_weight)
+    }
+
+    /// The M-step of the EM algorithm: the model maximizing the expected complete-data
+    /// log-likelihood given the sufficient statistics, which must have been accumulated
+    /// with this model.
+    pub fn m_step(&self, statistics: &SufficientStatistics) -> PPCAModel {
+        self.m_step_with_config(statistics, &PPCAFitConfig::default())
+    }
+
+    pub(crate) fn m_step_with_config(
+        &self,
+        statistics: &SufficientStatistics,
+        config: &PPCAFitConfig,
+    ) -> PPCAModel {
+        // Updated transform:
         let new_transform_rows = (0..self.output_size())
             .into_par_iter()
             .map(|idx| {
-                let total_second_moment = dataset
-                    .data
-                    .iter()
-                    .zip(&dataset.weights)
-                    .zip(&inferred)
-                    .filter(|((sample, _), _)| sample.mask.0[idx])
-                    .map(|((_, &weight), inferred)| weight * inferred.second_moment())
-                    // In case we get an empty dimension...
-                    .chain([DMatrix::zeros(self.state_size(), self.state_size())])
-                    .sum::<DMatrix<f64>>()
-                    + 1e-6 * DMatrix::<f64>::identity(self.state_size(), self.state_size());
-                let cross_moment_row = total_cross_moment.row(idx).transpose();
+                let total_second_moment = &statistics.second_moments[idx]
+                    + (1e-6 + config.l2_penalty)
+                        * DMatrix::<f64>::identity(self.state_size(), self.state_size());
+                let cross_moment_row = statistics.cross_moment.row(idx).transpose();
                 total_second_moment
                     .qr()
                     .solve(&cross_moment_row)
@@ -404,77 +1899,560 @@ impl PPCAModel {
         let new_transform = DMatrix::from_rows(&new_transform_rows);
 
         // Updated isotropic noise:
-        let (square_error, deviations_square_sum, total_deviation, totals) = dataset.data.par_iter().zip(&dataset.weights).zip(&inferred)
-        .filter(|((sample, _), _)| !sample.is_empty())
-        .map(
-            |((sample, &weight), inferred)| {
-            let sub_covariance = self.output_covariance.masked(&sample.mask);
-            let sub_transform = &*sub_covariance.transform;
-            let deviation = sample.mask.fillna(
-                &(sample.data_vector()
-                    - &*self.output_covariance.transform * &inferred.state
-                    - &self.mean),
-            );
-
-            (
-                weight * (sub_transform * &inferred.covariance).dot(&sub_transform),
-                weight * deviation.norm_squared(),
-                weight * deviation,
-                weight * sample.mask.as_vector(),
-            )
-        }).reduce_with(|
-            (square_error, deviation_square_sum, total_deviation, totals),
-            (square_error_, deviation_square_sum_, total_deviation_, totals_)| (
-                square_error + square_error_,
-                deviation_square_sum + deviation_square_sum_,
-                total_deviation + total_deviation_,
-                totals + totals_
-            )
-        ).expect("nonempty dataset");
-
-        let average_square_error = (square_error + deviations_square_sum) / totals.sum();
-        let new_mean =
-            total_deviation.zip_map(
-                &totals,
-                |sum, count| if count > 0.0 { sum / count } else { 0.0 },
-            ) + &self.mean;
+        let average_square_error =
+            (statistics.square_error + statistics.deviation_square_sum) / statistics.totals.sum();
+        let new_mean = statistics
+            .total_deviation
+            .zip_map(&statistics.totals, |sum, count| {
+                if count > 0.0 {
+                    sum / count
+                } else {
+                    0.0
+                }
+            })
+            + &self.mean;
 
         PPCAModel {
             output_covariance: OutputCovariance {
                 transform: Cow::Owned(new_transform),
-                isotropic_noise: average_square_error.sqrt(),
+                isotropic_noise: if config.fix_noise {
+                    self.output_covariance.isotropic_noise
+                } else {
+                    average_square_error.sqrt()
+                },
+            },
+            mean: if config.fix_mean {
+                self.mean.clone()
+            } else {
+                new_mean
             },
-            mean: new_mean,
         }
     }
 
-    pub fn to_canonical(&self) -> PPCAModel {
-        let mut svd = self
-            .output_covariance
-            .transform
-            .clone_owned()
-            .svd(true, false);
-        svd.v_t = Some(DMatrix::identity(self.state_size(), self.state_size()));
+    /// Runs `n_iter` EM iterations from this model and keeps every intermediate model,
+    /// starting with this one, together with its log-likelihood, e.g., to visualize the
+    /// convergence. This stores `n_iter + 1` models.
+    pub fn simulate_em_trajectory(
+        &self,
+        dataset: &Dataset,
+        n_iter: usize,
+    ) -> Vec<(PPCAModel, f64)> {
+        let mut trajectory = Vec::with_capacity(n_iter + 1);
+        let mut model = self.clone();
+
+        for _ in 0..n_iter {
+            let iterated = model.iterate(dataset);
+            let llk = model.llk(dataset);
+            trajectory.push((model, llk));
+            model = iterated;
+        }
+
+        let llk = model.llk(dataset);
+        trajectory.push((model, llk));
+
+        trajectory
+    }
+
+    /// Fits this model to a dataset with EM, starting from this model, as configured by
+    /// `config`. Returns the fitted model and the log-likelihood before the first
+    /// iteration and after each one.
+    pub fn fit(&self, dataset: &Dataset, config: &PPCAFitConfig) -> (PPCAModel, Vec<f64>) {
+        config.run(
+            self.clone(),
+            |model| model.llk(dataset),
+            |model| model.iterate_with_config(dataset, config),
+        )
+    }
+
+    /// Updates only the mean of this model with an exponentially decaying average,
+    /// `μ = (1 - decay) μ + decay x`, on the observed dimensions of `new_sample`. The
+    /// transform and the noise are kept, which suits data whose baseline drifts while its
+    /// structure stays the same.
+    pub fn update_mean_online(&self, new_sample: &MaskedSample, decay: f64) -> PPCAModel {
+        assert!((0.0..=1.0).contains(&decay), "decay must be in [0, 1]");
+        let mean = self
+            .mean
+            .iter()
+            .zip(new_sample.data.iter())
+            .zip(&new_sample.mask.0)
+            .map(|((&mean, &observed), selected)| {
+                if selected {
+                    (1.0 - decay) * mean + decay * observed
+                } else {
+                    mean
+                }
+            })
+            .collect::<Vec<_>>()
+            .into();
+
+        PPCAModel {
+            output_covariance: self.output_covariance.clone(),
+            mean,
+        }
+    }
 
-        let mut new_transform = svd.recompose().expect("all matrices were calculated");
-        // Flip new transform
-        for mut column in new_transform.column_iter_mut() {
-            column *= column.sum().signum();
+    /// A soft sequential update from a single observation, treating this model as a prior
+    /// worth `prior_strength` observations: the mean moves toward the posterior mean of
+    /// the observation (see `PPCAModel::extrapolate`) and the noise variance toward the
+    /// expected squared residual per observed dimension, both with weight
+    /// `1 / (1 + prior_strength)`. The transform is kept as it is.
+    ///
+    /// This departs from the original request, which asked for the noise variance to be
+    /// increased by `prior_strength * σ²` on every update. That grows `σ²` geometrically
+    /// with the number of observations and cannot converge toward the EM fit, so the
+    /// variance is instead averaged toward the residual of each observation.
+    pub fn observe_and_update(&self, observation: &MaskedSample, prior_strength: f64) -> PPCAModel {
+        assert!(prior_strength >= 0.0, "prior strength must be non-negative");
+        if observation.is_empty() {
+            return self.clone();
         }
 
+        let weight = 1.0 / (1.0 + prior_strength);
+        let inferred = self.infer_one(observation);
+        let posterior_mean = inferred.extrapolated(self, observation);
+
+        let sub_covariance = self.output_covariance.masked(&observation.mask);
+        let sub_transform = &*sub_covariance.transform;
+        let residual = observation
+            .mask
+            .mask(&(observation.data_vector() - &self.mean))
+            - sub_transform * &inferred.state;
+        let sample_noise_variance = (residual.norm_squared()
+            + (sub_transform * &inferred.covariance).dot(sub_transform))
+            / sub_covariance.output_size() as f64;
+        let noise_variance =
+            (1.0 - weight) * self.noise_variance() + weight * sample_noise_variance;
+
         PPCAModel {
             output_covariance: OutputCovariance::new_owned(
-                self.output_covariance.isotropic_noise,
-                new_transform,
+                noise_variance.sqrt(),
+                self.output_covariance.transform.clone().into_owned(),
             ),
-            mean: self.mean.clone(),
+            mean: &self.mean + weight * (posterior_mean - &self.mean),
         }
     }
-}
 
-#[derive(Debug)]
-pub struct InferredMasked {
-    state: DVector<f64>,
+    /// A cheap online update of this model with a single new sample, moving each
+    /// parameter towards the sufficient statistics of that sample with step
+    /// `learning_rate`, in the spirit of stochastic EM. With `z` and `Σ` the posterior
+    /// mean and covariance of the state and `e = x - μ - W z` on the observed dimensions:
+    /// * the mean moves towards the observed values, `μ += η (x - μ)`;
+    /// * the transform steps by `W += η (e zᵀ - W Σ)`, the rank-1 outer product `e zᵀ`
+    /// minus the posterior covariance correction `W Σ`, whose fixed point is the M-step of
+    /// `PPCAModel::iterate`;
+    /// * the noise variance is an exponential average of `(|e|² + tr(W Σ Wᵀ)) / n_observed`.
+    ///
+    /// Unobserved dimensions are left untouched. This costs `O(d k²)` per sample instead of
+    /// a pass over the whole dataset.
+    pub fn moment_matching_update(
+        &self,
+        new_sample: &MaskedSample,
+        learning_rate: f64,
+    ) -> PPCAModel {
+        assert!(
+            (0.0..=1.0).contains(&learning_rate),
+            "learning rate must be in [0, 1]"
+        );
+        if new_sample.is_empty() {
+            return self.clone();
+        }
+
+        let mask = &new_sample.mask;
+        let inferred = self.infer_one(new_sample);
+        let sub_sample = mask.mask(&(new_sample.data_vector() - &self.mean));
+        let sub_covariance = self.output_covariance.masked(mask);
+        let sub_transform = &*sub_covariance.transform;
+        let residual = &sub_sample - sub_transform * &inferred.state;
+
+        let transform_step = learning_rate
+            * (&residual * inferred.state.transpose() - sub_transform * &inferred.covariance);
+        let sample_noise_variance = (residual.norm_squared()
+            + (sub_transform * &inferred.covariance).dot(sub_transform))
+            / sub_covariance.output_size() as f64;
+        let noise_variance =
+            (1.0 - learning_rate) * self.noise_variance() + learning_rate * sample_noise_variance;
+
+        PPCAModel {
+            output_covariance: OutputCovariance::new_owned(
+                noise_variance.sqrt(),
+                &*self.output_covariance.transform + mask.expand_rows(&transform_step),
+            ),
+            mean: &self.mean + learning_rate * mask.expand(&sub_sample),
+        }
+    }
+
+    /// Performs one EM iteration with the sample weights given explicitly, replacing
+    /// whatever weights `dataset` carries. Each sample enters the sufficient statistics
+    /// of the M-step multiplied by its weight, so this is the same as calling
+    /// `PPCAModel::iterate` on `dataset.with_weights(weights)`.
+    pub fn iterate_weighted(&self, dataset: &Dataset, weights: &DVector<f64>) -> PPCAModel {
+        assert_eq!(
+            weights.len(),
+            dataset.len(),
+            "need exactly one weight per sample"
+        );
+        self.iterate(&dataset.with_weights(weights.iter().copied().collect()))
+    }
+
+    /// Rotates the hidden state so that the transform takes the form of PCA: with the SVD
+    /// `W = U S Vᵀ`, the new transform is `U S`, whose columns are orthogonal and sorted
+    /// by decreasing norm, the singular values. The rotation `V` is absorbed in the hidden
+    /// state, so the output distribution is unchanged. Columns are flipped to have a
+    /// non-negative sum, which makes the result unique for distinct singular values. If
+    /// the state is bigger than the output, the extra columns are zero.
+    pub fn to_canonical(&self) -> PPCAModel {
+        let svd = self
+            .output_covariance
+            .transform
+            .clone_owned()
+            .svd(true, false);
+        let u = svd.u.expect("left singular vectors were calculated");
+        let mut order = (0..svd.singular_values.len()).collect::<Vec<_>>();
+        order.sort_by(|&a, &b| svd.singular_values[b].total_cmp(&svd.singular_values[a]));
+
+        let mut new_transform = DMatrix::zeros(self.output_size(), self.state_size());
+        for (column, &idx) in order.iter().enumerate() {
+            let mut loading = u.column(idx) * svd.singular_values[idx];
+            loading *= loading.sum().signum();
+            new_transform.set_column(column, &loading);
+        }
+
+        PPCAModel {
+            output_covariance: OutputCovariance::new_owned(
+                self.output_covariance.isotropic_noise,
+                new_transform,
+            ),
+            mean: self.mean.clone(),
+        }
+    }
+
+    /// The marginal model of the dimensions `dims` of the output, in that order. The
+    /// marginal of a PPCA model is again a PPCA model, with the same noise and the
+    /// corresponding rows of the transform and of the mean. See also
+    /// `Dataset::project_to_dims`.
+    pub fn project_to_dims(&self, dims: &[usize]) -> PPCAModel {
+        assert!(
+            dims.iter().all(|&index| index < self.output_size()),
+            "dimension out of bounds"
+        );
+
+        PPCAModel::new(
+            self.output_covariance.isotropic_noise,
+            self.output_covariance.transform.select_rows(dims),
+            self.mean.select_rows(dims),
+        )
+    }
+
+    /// The marginal model of the pair of output dimensions `(dim_i, dim_j)`, in that
+    /// order, for plotting pairwise distributions. Unlike `PPCAModel::project_to_dims`,
+    /// the result is refitted with `PPCAModel::from_moments` from the `2×2` block of the
+    /// output covariance, so its state has size 1; any `2×2` covariance is exactly
+    /// represented this way, so the implied covariance is unchanged.
+    pub fn marginal_2d(&self, dim_i: usize, dim_j: usize) -> PPCAModel {
+        assert_ne!(dim_i, dim_j, "dimensions must be distinct");
+        let dims = [dim_i, dim_j];
+        let marginal = self.project_to_dims(&dims);
+
+        PPCAModel::from_moments(
+            marginal.mean.clone(),
+            &marginal.output_covariance.matrix(),
+            1,
+        )
+    }
+
+    /// Wraps this model in a mixture with this model as its single component, e.g., for
+    /// code written for mixtures. See also `PPCADistribution`.
+    pub fn as_mix(&self) -> PPCAMix {
+        PPCAMix::new(vec![self.clone()], DVector::zeros(1))
+    }
+
+    /// The scale `c` of an affine transform matrix `A` with `Aᵀ A = c I`, i.e., the
+    /// square of the factor multiplying an orthogonal matrix. Fails for any other `A`,
+    /// including singular ones.
+    fn affine_transform_scale(&self, a: &DMatrix<f64>) -> Result<f64, AffineTransformError> {
+        assert!(a.is_square(), "affine transform must be square");
+        assert_eq!(
+            a.nrows(),
+            self.output_size(),
+            "affine transform has wrong size"
+        );
+
+        let scale = a.norm_squared() / self.output_size() as f64;
+        let identity = DMatrix::<f64>::identity(self.output_size(), self.output_size());
+        let deviation = (a.transpose() * a - scale * &identity).norm() / (scale * identity.norm());
+        if scale > 0.0 && deviation <= AFFINE_ORTHOGONALITY_TOL {
+            Ok(scale)
+        } else {
+            Err(AffineTransformError { deviation })
+        }
+    }
+
+    /// Maps a model fitted on transformed data `x' = A x + b` back to a model of the
+    /// original data `x`: the new mean is `A⁻¹ (μ - b)` and the new transform `A⁻¹ W`,
+    /// computed by solving with the LU decomposition of `A`, and the new noise variance
+    /// is `σ² / c`. The transformed noise `σ² A⁻¹ A⁻ᵀ` is only isotropic when `A` is a
+    /// multiple of an orthogonal matrix, `Aᵀ A = c I` (e.g., a uniform scaling or a
+    /// rotation), so any other `A` is rejected. Log-likelihoods of the new model are those
+    /// of this model on the transformed data plus `ln |det A|`.
+    pub fn apply_affine_transform(
+        &self,
+        a: &DMatrix<f64>,
+        b: &DVector<f64>,
+    ) -> Result<PPCAModel, AffineTransformError> {
+        let scale = self.affine_transform_scale(a)?;
+        assert_eq!(b.len(), self.output_size(), "affine offset has wrong size");
+
+        let lu = a.clone().lu();
+        let new_transform = lu
+            .solve(&*self.output_covariance.transform)
+            .expect("affine transform is invertible");
+        let new_mean = lu
+            .solve(&(&self.mean - b))
+            .expect("affine transform is invertible");
+
+        Ok(PPCAModel::new(
+            self.noise_std() / scale.sqrt(),
+            new_transform,
+            new_mean,
+        ))
+    }
+
+    /// The inverse of `PPCAModel::apply_affine_transform`: maps a model of the data `x`
+    /// to a model of the transformed data `x' = A x + b`, with mean `A μ + b`, transform
+    /// `A W` and noise variance `c σ²`. As there, `A` must be a multiple of an orthogonal
+    /// matrix, `Aᵀ A = c I`, for the noise `σ² A Aᵀ` to stay isotropic.
+    pub fn apply_inverse_affine_transform(
+        &self,
+        a: &DMatrix<f64>,
+        b: &DVector<f64>,
+    ) -> Result<PPCAModel, AffineTransformError> {
+        let scale = self.affine_transform_scale(a)?;
+        assert_eq!(b.len(), self.output_size(), "affine offset has wrong size");
+
+        Ok(PPCAModel::new(
+            self.noise_std() * scale.sqrt(),
+            a * &*self.output_covariance.transform,
+            a * &self.mean + b,
+        ))
+    }
+
+    /// Rotates the hidden state so that the transform takes the form of PCA, with
+    /// orthogonal columns sorted by decreasing norm. This is `PPCAModel::to_canonical`,
+    /// under the name used in the PCA literature.
+    pub fn rotate_to_pca(&self) -> PPCAModel {
+        self.to_canonical()
+    }
+
+    /// Creates a model with the same mean and noise as this one but with a state of size
+    /// `new_state_size`, for warm-starting a search over the state size. The transform is
+    /// initialized from the principal directions of the current transform, in decreasing
+    /// order of singular value. If the new state is bigger, the extra columns are filled
+    /// with tiny random values (of size `CLONE_PADDING_SCALE * σ`) instead of zeros:
+    /// zero columns are a fixed point of EM and would never be learned. These are drawn
+    /// from `rng`.
+    pub fn clone_with_state_size<R: Rng + ?Sized>(
+        &self,
+        new_state_size: usize,
+        rng: &mut R,
+    ) -> PPCAModel {
+        let svd = self
+            .output_covariance
+            .transform
+            .clone_owned()
+            .svd(true, false);
+        let u = svd.u.expect("u was calculated");
+        let mut order = (0..svd.singular_values.len()).collect::<Vec<_>>();
+        order.sort_by(|&a, &b| svd.singular_values[b].total_cmp(&svd.singular_values[a]));
+
+        let n_kept = new_state_size.min(order.len());
+        let mut transform = DMatrix::zeros(self.output_size(), new_state_size);
+        for (column, &idx) in order.iter().take(n_kept).enumerate() {
+            transform.set_column(column, &(svd.singular_values[idx] * u.column(idx)));
+        }
+
+        if new_state_size > n_kept {
+            let padding = CLONE_PADDING_SCALE
+                * self.output_covariance.isotropic_noise
+                * standard_noise_matrix_with_rng(self.output_size(), new_state_size - n_kept, rng);
+            transform
+                .columns_mut(n_kept, new_state_size - n_kept)
+                .copy_from(&padding);
+        }
+
+        PPCAModel {
+            output_covariance: OutputCovariance::new_owned(
+                self.output_covariance.isotropic_noise,
+                transform,
+            ),
+            mean: self.mean.clone(),
+        }
+    }
+
+    /// Shrinks the transform of this model towards zero, i.e., `(1 - shrinkage) W`,
+    /// trading some bias for less variance in the estimated loadings. The `shrinkage`
+    /// must be in `[0, 1]`.
+    pub fn shrinkage_loadings(&self, shrinkage: f64) -> PPCAModel {
+        assert!(
+            (0.0..=1.0).contains(&shrinkage),
+            "shrinkage must be in [0, 1]"
+        );
+        PPCAModel {
+            output_covariance: OutputCovariance::new_owned(
+                self.output_covariance.isotropic_noise,
+                (1.0 - shrinkage) * &*self.output_covariance.transform,
+            ),
+            mean: self.mean.clone(),
+        }
+    }
+
+    /// Selects the shrinkage for `PPCAModel::shrinkage_loadings` minimizing the held-out
+    /// negative log-likelihood in a `n_folds` cross-validation. For each fold, this model
+    /// is refined with `SHRINKAGE_CV_ITERATIONS` EM iterations on the training set and
+    /// then every shrinkage in a grid of step `SHRINKAGE_CV_STEP` is scored on the
+    /// validation set.
+    pub fn optimal_shrinkage(&self, dataset: &Dataset, n_folds: usize) -> f64 {
+        let n_steps = (1.0 / SHRINKAGE_CV_STEP).round() as usize;
+        let shrinkages = (0..=n_steps)
+            .map(|step| step as f64 * SHRINKAGE_CV_STEP)
+            .collect::<Vec<_>>();
+
+        let held_out_llks = dataset
+            .folds(n_folds)
+            .into_iter()
+            .map(|(training, validation)| {
+                let mut model = self.clone();
+                for _ in 0..SHRINKAGE_CV_ITERATIONS {
+                    model = model.iterate(&training);
+                }
+
+                shrinkages
+                    .iter()
+                    .map(|&shrinkage| model.shrinkage_loadings(shrinkage).llk(&validation))
+                    .collect::<Vec<_>>()
+            })
+            .fold(vec![0.0; shrinkages.len()], |mut total, llks| {
+                for (total, llk) in total.iter_mut().zip(llks) {
+                    *total += llk;
+                }
+                total
+            });
+
+        shrinkages
+            .into_iter()
+            .zip(held_out_llks)
+            .fold(
+                (0.0, f64::NEG_INFINITY),
+                |(best, best_llk), (shrinkage, llk)| {
+                    if llk > best_llk {
+                        (shrinkage, llk)
+                    } else {
+                        (best, best_llk)
+                    }
+                },
+            )
+            .0
+    }
+}
+
+/// What happened in one EM iteration, as reported by `PPCAModel::em_step_diagnostics`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EMDiagnostics {
+    /// The log-likelihood of the dataset before the iteration.
+    pub llk_before: f64,
+    /// The log-likelihood of the dataset after the iteration.
+    pub llk_after: f64,
+    /// The improvement of the log-likelihood, which EM guarantees to be non-negative.
+    pub delta_llk: f64,
+    /// The Frobenius norm of the change in the loadings.
+    pub loading_change: f64,
+    /// The absolute change in the noise variance.
+    pub noise_change: f64,
+    /// The euclidean norm of the change in the mean.
+    pub mean_change: f64,
+    /// Whether the new parameters or log-likelihood contain NaNs.
+    pub has_nan: bool,
+    /// Whether the new parameters or log-likelihood contain infinities.
+    pub has_inf: bool,
+}
+
+/// The sufficient statistics of the E-step of the EM algorithm for a `PPCAModel`. These
+/// can be accumulated sample by sample and merged across workers, which allows for
+/// streaming and distributed EM. Create them with
+/// `PPCAModel::sufficient_statistics_empty` or `PPCAModel::e_step` and feed them to
+/// `PPCAModel::m_step`.
+#[derive(Debug, Clone)]
+pub struct SufficientStatistics {
+    /// Sum of `w (x - μ) E[z]ᵀ` over the observed dimensions.
+    cross_moment: DMatrix<f64>,
+    /// Sum of `w E[z zᵀ]` over the samples where each dimension is observed.
+    second_moments: Vec<DMatrix<f64>>,
+    /// Sum of `w tr(W Σ Wᵀ)` over the observed dimensions.
+    square_error: f64,
+    /// Sum of `w |x - W E[z] - μ|²` over the observed dimensions.
+    deviation_square_sum: f64,
+    /// Sum of `w (x - W E[z] - μ)` over the observed dimensions.
+    total_deviation: DVector<f64>,
+    /// Total weight of the observations of each dimension.
+    totals: DVector<f64>,
+}
+
+impl SufficientStatistics {
+    /// Adds the contribution of a single sample with the given weight, using `model`
+    /// for the inference. The model must be the same for all samples.
+    pub fn add_sample(
+        &mut self,
+        model: &PPCAModel,
+        sample: &MaskedSample,
+        weight: f64,
+    ) -> &mut SufficientStatistics {
+        if sample.is_empty() {
+            return self;
+        }
+
+        let inferred = model.infer_one(sample);
+        let centered_filled = sample.mask.fillna(&(sample.data_vector() - &model.mean));
+        self.cross_moment += weight * centered_filled * inferred.state.transpose();
+
+        let second_moment = weight * inferred.second_moment();
+        for (moment, selected) in self.second_moments.iter_mut().zip(&sample.mask.0) {
+            if selected {
+                *moment += &second_moment;
+            }
+        }
+
+        let sub_covariance = model.output_covariance.masked(&sample.mask);
+        let sub_transform = &*sub_covariance.transform;
+        let deviation = sample.mask.fillna(
+            &(sample.data_vector()
+                - &*model.output_covariance.transform * &inferred.state
+                - &model.mean),
+        );
+        self.square_error += weight * (sub_transform * &inferred.covariance).dot(sub_transform);
+        self.deviation_square_sum += weight * deviation.norm_squared();
+        self.total_deviation += weight * deviation;
+        self.totals += weight * sample.mask.as_vector();
+
+        self
+    }
+
+    /// Adds the statistics accumulated elsewhere (with the same model) to these ones.
+    pub fn merge(&mut self, other: &SufficientStatistics) -> &mut SufficientStatistics {
+        self.cross_moment += &other.cross_moment;
+        for (moment, other_moment) in self.second_moments.iter_mut().zip(&other.second_moments) {
+            *moment += other_moment;
+        }
+        self.square_error += other.square_error;
+        self.deviation_square_sum += other.deviation_square_sum;
+        self.total_deviation += &other.total_deviation;
+        self.totals += &other.totals;
+
+        self
+    }
+}
+
+#[derive(Debug)]
+pub struct InferredMasked {
+    state: DVector<f64>,
     covariance: DMatrix<f64>,
 }
 
@@ -626,6 +2604,23 @@ mod test {
         )
     }
 
+    #[test]
+    fn test_n_parameters() {
+        // 6 for the transform, less 1 for the rotation, 3 for the mean, 1 for the noise.
+        assert_eq!(toy_model().n_parameters(), 9);
+
+        let model = |output_size, state_size| {
+            PPCAModel::new(
+                1.0,
+                DMatrix::zeros(output_size, state_size),
+                DVector::zeros(output_size),
+            )
+        };
+        assert_eq!(model(3, 1).n_parameters(), 7);
+        assert_eq!(model(10, 3).n_parameters(), 30 - 3 + 10 + 1);
+        assert_eq!(model(3, 0).n_parameters(), 4);
+    }
+
     #[test]
     fn test_quadratic_form() {
         let output_covariance = output_covariance();
@@ -641,6 +2636,1638 @@ mod test {
         approx::assert_relative_eq!(output_covariance.covariance_log_det(), -3.49328);
     }
 
+    #[test]
+    fn test_degrees_of_freedom() {
+        let model = toy_model();
+        let dataset = model.sample_with_seed(20_000, 0.0, 0);
+        let dof = model.degrees_of_freedom_observation(&dataset.data[0]);
+        assert_eq!(dof, 1);
+
+        // The normalized reconstruction error should behave like a chi-squared
+        // variable with this many degrees of freedom: its mean is the number of degrees
+        // of freedom, and about 5% of the values exceed its 95% quantile.
+        let statistics = dataset
+            .data
+            .iter()
+            .map(|sample| {
+                let residual = sample.data_vector() - model.infer_one(sample).smoothed(&model);
+                residual.norm_squared() / model.noise_variance()
+            })
+            .collect::<Vec<_>>();
+        let mean_statistic = statistics.iter().sum::<f64>() / statistics.len() as f64;
+        approx::assert_abs_diff_eq!(mean_statistic, dof as f64, epsilon = 0.1);
+
+        let exceeding = statistics
+            .iter()
+            .filter(|&&statistic| crate::math::chi_squared_sf(statistic, dof as f64) < 0.05)
+            .count();
+        approx::assert_abs_diff_eq!(
+            exceeding as f64 / statistics.len() as f64,
+            0.05,
+            epsilon = 0.01
+        );
+    }
+
+    #[test]
+    fn test_param_vector_round_trip() {
+        let model = toy_model();
+        let params = model.param_vector();
+        assert_eq!(params.len(), 3 * 2 + 3 + 1);
+
+        let reconstructed = PPCAModel::from_param_vector(3, 2, &params);
+        approx::assert_relative_eq!(*reconstructed.loadings(), *model.loadings());
+        approx::assert_relative_eq!(*reconstructed.output_mean(), *model.output_mean());
+        approx::assert_relative_eq!(reconstructed.noise_variance(), model.noise_variance());
+    }
+
+    #[test]
+    fn test_neg_llk_gradient() {
+        let dataset = toy_model().sample(50, 0.2);
+        let model = PPCAModel::init(2, &dataset);
+        let (neg_llk, gradient) = model.neg_llk_and_gradient(&dataset);
+        approx::assert_relative_eq!(neg_llk, -model.llk(&dataset), max_relative = 1e-12);
+
+        let params = model.param_vector();
+        let epsilon = 1e-6;
+
+        for idx in 0..params.len() {
+            let mut plus = params.clone();
+            plus[idx] += epsilon;
+            let mut minus = params.clone();
+            minus[idx] -= epsilon;
+            let numerical = (PPCAModel::from_param_vector(3, 2, &minus).llk(&dataset)
+                - PPCAModel::from_param_vector(3, 2, &plus).llk(&dataset))
+                / (2.0 * epsilon);
+
+            approx::assert_relative_eq!(
+                gradient[idx],
+                numerical,
+                epsilon = 1e-4,
+                max_relative = 1e-4
+            );
+        }
+    }
+
+    #[test]
+    fn test_score_samples() {
+        let model = toy_model();
+        let dataset = model.sample(20_000, 0.2);
+        let scores = model.score_samples(&dataset);
+        assert_eq!(scores.shape(), (20_000, 10));
+
+        let (_, gradient) = model.neg_llk_and_gradient(&dataset);
+        approx::assert_relative_eq!(
+            scores.row_sum().transpose(),
+            -gradient,
+            epsilon = 1e-6,
+            max_relative = 1e-9
+        );
+
+        // The expected score under the true model is zero.
+        let n_samples = dataset.len() as f64;
+        for column in scores.column_iter() {
+            let mean = column.mean();
+            let std = column.variance().sqrt();
+            assert!(mean.abs() < 5.0 * std / n_samples.sqrt());
+        }
+    }
+
+    #[test]
+    fn test_folds() {
+        let dataset = toy_model().sample(10, 0.0);
+        let folds = dataset.folds(3);
+        assert_eq!(folds.len(), 3);
+        assert_eq!(
+            folds
+                .iter()
+                .map(|(training, validation)| (training.len(), validation.len()))
+                .collect::<Vec<_>>(),
+            vec![(6, 4), (7, 3), (7, 3)]
+        );
+    }
+
+    #[test]
+    fn test_shrinkage_loadings() {
+        let model = toy_model();
+        approx::assert_relative_eq!(*model.shrinkage_loadings(0.0).loadings(), *model.loadings());
+        approx::assert_relative_eq!(
+            *model.shrinkage_loadings(0.25).loadings(),
+            0.75 * model.loadings()
+        );
+        assert!(model
+            .shrinkage_loadings(1.0)
+            .loadings()
+            .iter()
+            .all(|&w| w == 0.0));
+
+        let shrinkage = model.optimal_shrinkage(&model.sample(500, 0.2), 5);
+        assert!((0.0..=1.0).contains(&shrinkage));
+    }
+
+    #[test]
+    fn test_iterate_weighted() {
+        let model = toy_model();
+        let dataset = model.sample(100, 0.2);
+        let weights = DVector::from_fn(100, |idx, _| 0.5 + (idx % 3) as f64);
+
+        let iterated = model.iterate_weighted(&dataset, &weights);
+        let expected = model.iterate(&dataset.with_weights(weights.iter().copied().collect()));
+        approx::assert_relative_eq!(
+            *iterated.loadings(),
+            *expected.loadings(),
+            max_relative = 1e-10
+        );
+        approx::assert_relative_eq!(
+            *iterated.output_mean(),
+            *expected.output_mean(),
+            max_relative = 1e-10
+        );
+        approx::assert_relative_eq!(
+            iterated.noise_variance(),
+            expected.noise_variance(),
+            max_relative = 1e-10
+        );
+
+        // Unit weights are the unweighted update.
+        let unweighted = model.iterate_weighted(&dataset, &DVector::repeat(100, 1.0));
+        approx::assert_relative_eq!(
+            *unweighted.loadings(),
+            *model.iterate(&dataset).loadings(),
+            max_relative = 1e-10
+        );
+    }
+
+    #[test]
+    fn test_iterate_empty_dataset() {
+        let model = toy_model();
+        let iterated = model.iterate(&Dataset::new(vec![]));
+        assert_eq!(iterated.noise_variance(), model.noise_variance());
+        assert_eq!(iterated.loadings(), model.loadings());
+        assert_eq!(iterated.output_mean(), model.output_mean());
+    }
+
+    #[test]
+    fn test_conditional_llk() {
+        let model = toy_model();
+        let sample = MaskedSample::new(
+            dvector![0.5, 1.5, -0.3],
+            Mask(BitVec::from_iter([true, true, false])),
+        );
+
+        let covariance = model.loadings() * model.loadings().transpose()
+            + model.noise_variance() * DMatrix::<f64>::identity(3, 3);
+        let sub_covariance = covariance.slice((0, 0), (2, 2)).clone_owned();
+        let residual = dvector![0.5, 1.5 - 1.0];
+        let expected = -residual.dot(&(sub_covariance.clone().try_inverse().unwrap() * &residual))
+            / 2.0
+            - sub_covariance.determinant().ln() / 2.0
+            - LN_2PI;
+
+        approx::assert_relative_eq!(model.conditional_llk(&sample), expected, epsilon = 1e-9);
+    }
+
+    #[test]
+    fn test_moment_matching_update() {
+        let model = toy_model();
+        let dataset = model.sample(5_000, 0.2);
+
+        // An empty sample changes nothing.
+        let empty = MaskedSample::new(dvector![0.0, 0.0, 0.0], Mask(BitVec::from_elem(3, false)));
+        let updated = model.moment_matching_update(&empty, 0.5);
+        approx::assert_relative_eq!(*updated.loadings(), *model.loadings());
+
+        // Starting from the true model, small steps should hover around the MLE.
+        let streamed = dataset.data.iter().fold(model.clone(), |model, sample| {
+            model.moment_matching_update(sample, 1e-3)
+        });
+        let n_samples = dataset.len() as f64;
+        approx::assert_abs_diff_eq!(
+            streamed.llk(&dataset) / n_samples,
+            model.llk(&dataset) / n_samples,
+            epsilon = 0.1
+        );
+    }
+
+    #[test]
+    fn test_moment_matching_update_approaches_batch_em() {
+        let model = toy_model();
+        let dataset = model.sample_with_seed(2_000, 0.2, 0);
+        let start = PPCAModel::new(
+            0.5,
+            dmatrix![
+                0.5, 1.5, 0.3;
+                1.2, -0.2, 0.8;
+            ]
+            .transpose(),
+            dvector![0.5, 0.0, -0.5],
+        );
+
+        let mut batch = start.clone();
+        for _ in 0..200 {
+            batch = batch.iterate(&dataset);
+        }
+
+        let mut streamed = start.clone();
+        for _ in 0..10 {
+            for sample in dataset.iter() {
+                streamed = streamed.moment_matching_update(sample, 5e-3);
+            }
+        }
+
+        let n_samples = dataset.len() as f64;
+        let batch_llk = batch.llk(&dataset) / n_samples;
+        let streamed_llk = streamed.llk(&dataset) / n_samples;
+        let start_llk = start.llk(&dataset) / n_samples;
+        assert!(batch_llk - streamed_llk < 0.1 * (batch_llk - start_llk));
+        approx::assert_abs_diff_eq!(
+            streamed.output_covariance.matrix(),
+            batch.output_covariance.matrix(),
+            epsilon = 0.2
+        );
+        approx::assert_abs_diff_eq!(streamed.mean, batch.mean, epsilon = 0.1);
+    }
+
+    #[test]
+    fn test_balance_by_weights() {
+        let model = toy_model();
+        let dataset = model
+            .sample(200, 0.2)
+            .with_importance_weights(DVector::from_fn(200, |idx, _| 1.0 + (idx % 7) as f64));
+        let balanced = dataset.balance_by_weights();
+        approx::assert_relative_eq!(balanced.weights.iter().sum::<f64>(), 200.0, epsilon = 1e-9);
+
+        let iterated = model.iterate(&dataset);
+        let iterated_balanced = model.iterate(&balanced);
+        approx::assert_relative_eq!(
+            *iterated_balanced.loadings(),
+            *iterated.loadings(),
+            max_relative = 1e-6
+        );
+        approx::assert_relative_eq!(
+            *iterated_balanced.output_mean(),
+            *iterated.output_mean(),
+            max_relative = 1e-6
+        );
+        approx::assert_relative_eq!(
+            iterated_balanced.noise_variance(),
+            iterated.noise_variance(),
+            max_relative = 1e-6
+        );
+    }
+
+    #[test]
+    fn test_generate_synthetic() {
+        let (model, dataset) = PPCAModel::generate_synthetic(5, 2, 100, 0.1, 42);
+        assert_eq!(model.output_size(), 5);
+        assert_eq!(model.state_size(), 2);
+        assert_eq!(dataset.len(), 100);
+        assert!(dataset.data.iter().all(|sample| sample.mask().0.all()));
+
+        let (same_model, same_dataset) = PPCAModel::generate_synthetic(5, 2, 100, 0.1, 42);
+        assert_eq!(same_model.param_vector(), model.param_vector());
+        assert!(dataset
+            .data
+            .iter()
+            .zip(&*same_dataset.data)
+            .all(|(sample, same)| sample.data_vector() == same.data_vector()));
+    }
+
+    #[test]
+    fn test_llk_single() {
+        let model = toy_model();
+        let dataset = model.sample(50, 0.3);
+        let llks = model.llks(&dataset);
+
+        for (sample, &llk) in dataset.data.iter().zip(&llks) {
+            assert_eq!(model.llk_single(sample), llk);
+        }
+    }
+
+    #[test]
+    fn test_update_mean_online() {
+        let model = toy_model();
+        let dataset = model.sample(5_000, 0.2);
+
+        let mut estimate = model.clone();
+        estimate.set_mean(DVector::zeros(3));
+        let estimate = dataset.data.iter().fold(estimate, |estimate, sample| {
+            estimate.update_mean_online(sample, 0.01)
+        });
+
+        approx::assert_abs_diff_eq!(*estimate.output_mean(), *model.output_mean(), epsilon = 0.5);
+        approx::assert_relative_eq!(*estimate.loadings(), *model.loadings());
+        assert_eq!(estimate.noise_variance(), model.noise_variance());
+    }
+
+    #[test]
+    fn test_drop_missing() {
+        let dataset = toy_model().sample(100, 0.2);
+        let fully_observed = dataset.drop_missing();
+        assert_eq!(
+            dataset.n_fully_observed() + dataset.n_has_missing(),
+            dataset.len()
+        );
+        assert_eq!(fully_observed.len(), dataset.n_fully_observed());
+        assert_eq!(fully_observed.n_has_missing(), 0);
+        assert!(fully_observed
+            .missing_mask_matrix()
+            .iter()
+            .all(|&missing| !missing));
+        assert_eq!(
+            dataset
+                .missing_mask_matrix()
+                .iter()
+                .filter(|&&missing| missing)
+                .count(),
+            dataset
+                .data
+                .iter()
+                .map(|sample| sample.mask().0.iter().filter(|&selected| !selected).count())
+                .sum::<usize>()
+        );
+    }
+
+    #[test]
+    fn test_dataset_iter() {
+        let dataset = toy_model().sample(100, 0.2);
+        assert_eq!(dataset.iter().count(), dataset.len());
+
+        // Compare bits, since masked values are NaN.
+        let sequential = dataset
+            .iter()
+            .map(|sample| sample.masked_vector().map(f64::to_bits))
+            .collect::<Vec<_>>();
+        let parallel = dataset
+            .par_iter()
+            .map(|sample| sample.masked_vector().map(f64::to_bits))
+            .collect::<Vec<_>>();
+        assert_eq!(parallel, sequential);
+    }
+
+    #[test]
+    fn test_missingness_fraction() {
+        let sample = MaskedSample::new(
+            dvector![1.0, 2.0, 3.0, 4.0],
+            Mask(BitVec::from_iter([true, false, true, false])),
+        );
+        assert_eq!(sample.dimension(), 4);
+        assert_eq!(sample.n_observed(), 2);
+        assert_eq!(sample.n_missing(), 2);
+        assert_eq!(sample.missingness_fraction(), 0.5);
+
+        for sample in toy_model().sample(100, 0.3).iter() {
+            assert_eq!(sample.n_observed() + sample.n_missing(), sample.dimension());
+            assert!((0.0..=1.0).contains(&sample.missingness_fraction()));
+        }
+    }
+
+    #[test]
+    fn test_clone_with_state_size() {
+        let model = toy_model();
+        let dataset = model.sample_with_seed(100, 0.2, 0);
+        let covariance = |model: &PPCAModel| model.loadings() * model.loadings().transpose();
+        let mut rng = StdRng::seed_from_u64(0);
+
+        // Same size: only the latent space is rotated.
+        let same = model.clone_with_state_size(2, &mut rng);
+        approx::assert_relative_eq!(covariance(&same), covariance(&model), epsilon = 1e-9);
+        approx::assert_relative_eq!(same.llk(&dataset), model.llk(&dataset), epsilon = 1e-6);
+
+        // Smaller: keep the principal direction, whose squared norm is the biggest
+        // eigenvalue of `W Wᵀ`, i.e., 3.
+        let smaller = model.clone_with_state_size(1, &mut rng);
+        assert_eq!(smaller.state_size(), 1);
+        approx::assert_relative_eq!(smaller.loadings().norm_squared(), 3.0, epsilon = 1e-9);
+        approx::assert_relative_eq!(*smaller.output_mean(), *model.output_mean());
+        assert_eq!(smaller.noise_variance(), model.noise_variance());
+
+        // Bigger: tiny extra columns barely change the distribution.
+        let bigger = model.clone_with_state_size(3, &mut rng);
+        assert_eq!(bigger.state_size(), 3);
+        assert!(bigger.loadings().column(2).norm() > 0.0);
+        approx::assert_relative_eq!(
+            bigger.llk(&dataset),
+            model.llk(&dataset),
+            max_relative = 1e-2
+        );
+    }
+
+    #[test]
+    fn test_from_moments() {
+        let model = toy_model();
+        let covariance = model.loadings() * model.loadings().transpose()
+            + model.noise_variance() * DMatrix::<f64>::identity(3, 3);
+        let recovered = PPCAModel::from_moments(model.output_mean().clone(), &covariance, 2);
+
+        approx::assert_relative_eq!(recovered.noise_variance(), 0.01, epsilon = 1e-9);
+        approx::assert_relative_eq!(
+            recovered.loadings() * recovered.loadings().transpose(),
+            model.loadings() * model.loadings().transpose(),
+            epsilon = 1e-9
+        );
+        approx::assert_relative_eq!(*recovered.output_mean(), *model.output_mean());
+    }
+
+    #[test]
+    fn test_sample_moments() {
+        let model = toy_model();
+        let dataset = model.sample(20_000, 0.2);
+        let covariance = model.loadings() * model.loadings().transpose()
+            + model.noise_variance() * DMatrix::<f64>::identity(3, 3);
+
+        approx::assert_abs_diff_eq!(dataset.sample_mean(), *model.output_mean(), epsilon = 0.1);
+        approx::assert_abs_diff_eq!(dataset.sample_covariance(), covariance, epsilon = 0.15);
+    }
+
+    #[test]
+    fn test_fit() {
+        let model = toy_model();
+        let dataset = model.sample(200, 0.2);
+        let init = PPCAModel::init(2, &dataset);
+
+        let config = PPCAFitConfig::default().max_iter(5).tol(0.0);
+        let (fitted, llks) = init.fit(&dataset, &config);
+        assert_eq!(llks.len(), 6);
+        approx::assert_relative_eq!(llks[5], fitted.llk(&dataset), max_relative = 1e-10);
+
+        let config = config.fix_mean(true).fix_noise(true);
+        let (fitted, _) = init.fit(&dataset, &config);
+        assert_eq!(fitted.output_mean(), init.output_mean());
+        assert_eq!(fitted.noise_variance(), init.noise_variance());
+
+        // A big penalty shrinks the transform.
+        let (penalized, _) = init.fit(&dataset, &config.l2_penalty(1e6));
+        assert!(penalized.loadings().norm() < 1e-2 * init.loadings().norm());
+    }
+
+    #[test]
+    fn test_sufficient_statistics() {
+        let model = toy_model();
+        let dataset = model.sample(100, 0.2);
+
+        let mut statistics = model.sufficient_statistics_empty();
+        for (sample, &weight) in dataset.data.iter().zip(&dataset.weights) {
+            statistics.add_sample(&model, sample, weight);
+        }
+        let streamed = model.m_step(&statistics);
+        let batch = model.m_step(&model.e_step(&dataset));
+
+        approx::assert_relative_eq!(
+            *streamed.loadings(),
+            *batch.loadings(),
+            max_relative = 1e-10
+        );
+        approx::assert_relative_eq!(
+            *streamed.output_mean(),
+            *batch.output_mean(),
+            epsilon = 1e-12,
+            max_relative = 1e-10
+        );
+        approx::assert_relative_eq!(
+            streamed.noise_variance(),
+            batch.noise_variance(),
+            max_relative = 1e-10
+        );
+
+        // Merging two halves is the same as accumulating everything.
+        let (first, second) = dataset.data.split_at(50);
+        let mut merged = model.e_step(&Dataset::new(first.to_vec()));
+        merged.merge(&model.e_step(&Dataset::new(second.to_vec())));
+        approx::assert_relative_eq!(
+            *model.m_step(&merged).loadings(),
+            *batch.loadings(),
+            max_relative = 1e-10
+        );
+    }
+
+    #[test]
+    fn test_sample_conditional() {
+        let model = toy_model();
+        let sample = MaskedSample::new(
+            dvector![0.5, 0.0, -0.3],
+            Mask(BitVec::from_iter([true, false, true])),
+        );
+        let conditional_mean = model.infer_one(&sample).extrapolated(&model, &sample);
+
+        let n_draws = 2_000;
+        let mean = (0..n_draws)
+            .map(|seed| {
+                let drawn = model.sample_conditional(&sample, seed);
+                assert!(drawn.mask().0.all());
+                assert_eq!(drawn.data_vector()[0], 0.5);
+                assert_eq!(drawn.data_vector()[2], -0.3);
+                drawn.data_vector()
+            })
+            .sum::<DVector<f64>>()
+            / n_draws as f64;
+
+        approx::assert_abs_diff_eq!(mean, conditional_mean, epsilon = 0.05);
+    }
+
+    #[test]
+    fn test_complete_data_sufficient_statistics() {
+        let model = toy_model();
+        let dataset = model.sample(100, 0.0);
+        let (states, second_moment, total_weight) =
+            model.complete_data_sufficient_statistics(&dataset);
+
+        assert_eq!(states.shape(), (100, 2));
+        assert_eq!(second_moment.shape(), (2, 2));
+        assert_eq!(total_weight, 100.0);
+        approx::assert_relative_eq!(second_moment, second_moment.transpose());
+
+        // With no missing values, the updated loadings solve `W Σ E[z zᵀ] = Σ (x - μ) E[z]ᵀ`.
+        let mut centered = DMatrix::zeros(100, 3);
+        for (mut row, sample) in centered.row_iter_mut().zip(dataset.iter()) {
+            row.copy_from(&(sample.data_vector() - model.output_mean()).transpose());
+        }
+        let cross_moment = centered.transpose() * &states;
+        let loadings = (second_moment + 1e-6 * DMatrix::<f64>::identity(2, 2))
+            .qr()
+            .solve(&cross_moment.transpose())
+            .expect("second moment is invertible")
+            .transpose();
+
+        approx::assert_relative_eq!(
+            loadings,
+            *model.iterate(&dataset).loadings(),
+            max_relative = 1e-8
+        );
+    }
+
+    #[test]
+    fn test_covariance_distance() {
+        let (_, dataset) = PPCAModel::generate_synthetic(6, 3, 2_000, 0.1, 7);
+        let mean = dataset.sample_mean();
+        let covariance = dataset.sample_covariance();
+
+        let distances = (1..=3)
+            .map(|state_size| {
+                PPCAModel::from_moments(mean.clone(), &covariance, state_size)
+                    .covariance_distance(&dataset)
+            })
+            .collect::<Vec<_>>();
+
+        assert!(distances.iter().all(|&distance| distance >= 0.0));
+        assert!(distances[0] > distances[1]);
+        assert!(distances[1] > distances[2]);
+        assert!(distances[2] < 1e-2);
+    }
+
+    #[test]
+    fn test_sample_one_unmasked() {
+        let model = toy_model();
+        let sample = model.sample_one_unmasked(Some(3));
+        assert_eq!(sample.n_missing(), 0);
+        assert_eq!(sample.dimension(), 3);
+        assert_eq!(
+            model.sample_one_unmasked(Some(3)).data_vector(),
+            sample.data_vector()
+        );
+        assert_eq!(model.sample_one_unmasked(None).n_missing(), 0);
+    }
+
+    #[test]
+    fn test_posterior_mode() {
+        let model = toy_model();
+        let sample = MaskedSample {
+            data: dvector![1.0, 2.0, 0.0],
+            mask: Mask(BitVec::from_iter([true, true, false])),
+        };
+        let mode = model.posterior_mode(&sample);
+        approx::assert_relative_eq!(mode, *model.infer_one(&sample).state());
+
+        // The gradient of the joint log-density `log p(x_observed, z)` vanishes at the mode.
+        let transform = model.loadings().rows(0, 2).clone_owned();
+        let residual = dvector![1.0, 2.0] - &transform * &mode - model.output_mean().rows(0, 2);
+        let gradient = transform.transpose() * residual / model.noise_variance() - &mode;
+        approx::assert_abs_diff_eq!(gradient, DVector::zeros(2), epsilon = 1e-8);
+    }
+
+    #[test]
+    fn test_with_mask_probability() {
+        let dataset = toy_model().sample(2_000, 0.0);
+        assert_eq!(dataset.with_mask_probability(0.0, 1).n_has_missing(), 0);
+
+        let masked = dataset.with_mask_probability(0.3, 1);
+        assert_eq!(masked.len(), dataset.len());
+        let n_missing = masked.iter().map(MaskedSample::n_missing).sum::<usize>();
+        let fraction = n_missing as f64 / (3 * dataset.len()) as f64;
+        // The standard deviation of the fraction is about 0.006.
+        approx::assert_abs_diff_eq!(fraction, 0.3, epsilon = 0.03);
+
+        for (sample, masked_sample) in dataset.iter().zip(masked.iter()) {
+            for (idx, selected) in masked_sample.mask().0.iter().enumerate() {
+                if selected {
+                    assert_eq!(masked_sample.data_vector()[idx], sample.data_vector()[idx]);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_em_step_diagnostics() {
+        let dataset = toy_model().sample(500, 0.2);
+        let mut model = PPCAModel::init(2, &dataset);
+
+        let (_, first) = model.em_step_diagnostics(&dataset);
+        for _ in 0..200 {
+            let (iterated, diagnostics) = model.em_step_diagnostics(&dataset);
+            assert!(!diagnostics.has_nan);
+            assert!(!diagnostics.has_inf);
+            assert!(diagnostics.delta_llk >= -1e-6 * diagnostics.llk_before.abs());
+            approx::assert_relative_eq!(
+                diagnostics.llk_after,
+                iterated.llk(&dataset),
+                max_relative = 1e-10
+            );
+            model = iterated;
+        }
+
+        let (_, last) = model.em_step_diagnostics(&dataset);
+        assert!(last.delta_llk < first.delta_llk);
+        assert!(last.loading_change < first.loading_change);
+        assert!(last.noise_change < first.noise_change);
+        assert!(last.mean_change < first.mean_change);
+    }
+
+    #[test]
+    fn test_project_and_reconstruct() {
+        let model = toy_model();
+        let dataset = model.sample(50, 0.3);
+        let (states, reconstructed) = model.project_and_reconstruct(&dataset);
+
+        assert_eq!(states.shape(), (50, 2));
+        approx::assert_relative_eq!(states, model.project(&dataset));
+        for (sample, expected) in reconstructed
+            .iter()
+            .zip(model.reconstruct(&states).iter())
+            .chain(reconstructed.iter().zip(model.smooth(&dataset).iter()))
+        {
+            assert_eq!(sample.n_missing(), 0);
+            approx::assert_relative_eq!(sample.data_vector(), expected.data_vector());
+        }
+    }
+
+    #[test]
+    fn test_interpolate() {
+        let model = toy_model();
+        let from = MaskedSample::unmasked(dvector![1.0, 2.0, 0.0]);
+        let to = MaskedSample {
+            data: dvector![0.0, 0.0, -1.0],
+            mask: Mask(BitVec::from_iter([false, true, true])),
+        };
+        let path = model.interpolate(&from, &to, 5);
+        assert_eq!(path.len(), 5);
+
+        let points = path
+            .iter()
+            .map(MaskedSample::data_vector)
+            .collect::<Vec<_>>();
+        approx::assert_relative_eq!(points[0], model.smooth_one(&from).data_vector());
+        approx::assert_relative_eq!(points[4], model.smooth_one(&to).data_vector());
+        approx::assert_relative_eq!(points[2], (&points[0] + &points[4]) / 2.0);
+    }
+
+    #[test]
+    fn test_marginal_llk_latent_subset() {
+        let model = toy_model();
+        let dataset = model.sample(20, 0.2);
+        approx::assert_relative_eq!(
+            model.marginal_llk_latent_subset(&dataset, &[0, 1]),
+            model.llks(&dataset)
+        );
+
+        let reduced = PPCAModel::new(
+            0.1,
+            dmatrix![1.0, 0.0, 1.0].transpose(),
+            dvector![0.0, 1.0, 0.0],
+        );
+        approx::assert_relative_eq!(
+            model.marginal_llk_latent_subset(&dataset, &[1]),
+            reduced.llks(&dataset)
+        );
+    }
+
+    #[test]
+    fn test_information_gain_per_feature() {
+        let model = toy_model();
+        let gains = model.information_gain_per_feature();
+        assert_eq!(gains.len(), 3);
+        assert!(gains[0] > gains[1]);
+        approx::assert_relative_eq!(gains[1], gains[2]);
+
+        // The prior entropy of the state minus the posterior entropy given one dimension.
+        for idx in 0..3 {
+            let mut mask = BitVec::from_elem(3, false);
+            mask.set(idx, true);
+            let sample = MaskedSample {
+                data: dvector![0.5, 0.5, 0.5],
+                mask: Mask(mask),
+            };
+            let posterior_log_det = model.infer_one(&sample).covariance().determinant().ln();
+            approx::assert_relative_eq!(gains[idx], -posterior_log_det / 2.0, max_relative = 1e-8);
+        }
+    }
+
+    #[test]
+    fn test_project_to_2d() {
+        let model = PPCAModel::new(
+            0.1,
+            dmatrix![
+                1.0, 0.0, 3.0;
+                0.0, 0.5, 0.0;
+                1.0, 0.0, 0.0;
+                0.0, 0.0, 2.0;
+            ],
+            DVector::zeros(4),
+        );
+        let dataset = model.sample(30, 0.1);
+        let projected = model.project_to_2d(&dataset);
+        assert_eq!(projected.shape(), (30, 2));
+
+        let columns = model.largest_loading_columns(2);
+        assert_eq!(columns, vec![2, 0]);
+        let loadings_2d = model.loadings().select_columns(&columns);
+        let norms = model
+            .loadings()
+            .column_iter()
+            .map(|column| column.norm())
+            .collect::<Vec<_>>();
+        approx::assert_relative_eq!(loadings_2d.column(0).norm(), norms[2]);
+        approx::assert_relative_eq!(loadings_2d.column(1).norm(), norms[0]);
+        assert!(norms[1] < norms[0]);
+
+        let states = model.project(&dataset);
+        approx::assert_relative_eq!(projected.column(0), states.column(2));
+        approx::assert_relative_eq!(projected.column(1), states.column(0));
+    }
+
+    #[test]
+    fn test_kl_divergence() {
+        let model = toy_model();
+        let other = PPCAModel::new(
+            0.5,
+            dmatrix![2.0, 0.0, 1.0].transpose(),
+            dvector![1.0, 0.0, 0.0],
+        );
+        approx::assert_abs_diff_eq!(model.kl_divergence(&model), 0.0, epsilon = 1e-10);
+
+        let covariance = model.output_covariance.matrix();
+        let other_covariance = other.output_covariance.matrix();
+        let other_precision = other_covariance
+            .clone()
+            .try_inverse()
+            .expect("is invertible");
+        let difference = &other.mean - &model.mean;
+        let expected = ((&other_precision * &covariance).trace()
+            + (difference.transpose() * &other_precision * &difference)[(0, 0)]
+            - 3.0
+            + other_covariance.determinant().ln()
+            - covariance.determinant().ln())
+            / 2.0;
+        approx::assert_relative_eq!(model.kl_divergence(&other), expected, max_relative = 1e-8);
+    }
+
+    #[test]
+    fn test_predict_from_partial() {
+        let model = toy_model();
+        let (mean, std) = model.predict_from_partial(&[2, 0], &dvector![0.5, 1.0], &[1, 0]);
+
+        let sample = MaskedSample {
+            data: dvector![1.0, 0.0, 0.5],
+            mask: Mask(BitVec::from_iter([true, false, true])),
+        };
+        let inferred = model.infer_one(&sample);
+        let smoothed = inferred.smoothed(&model);
+        let variance = inferred.extrapolated_covariance_diagonal(&model, &sample);
+
+        approx::assert_relative_eq!(mean, dvector![smoothed[1], 1.0]);
+        approx::assert_relative_eq!(std, dvector![variance[1].sqrt(), 0.0]);
+        assert!(std[0] > 0.0);
+    }
+
+    #[test]
+    fn test_sample_with_seed() {
+        let model = toy_model();
+        let dataset = model.sample_with_seed(100_000, 0.0, 11);
+        assert_eq!(
+            dataset.to_dense_matrix(0.0),
+            model
+                .sample_with_seed(100_000, 0.0, 11)
+                .to_dense_matrix(0.0)
+        );
+
+        let matrix = dataset.to_dense_matrix(0.0);
+        let mean = matrix.row_mean().transpose();
+        let centered = DMatrix::from_fn(matrix.nrows(), 3, |i, j| matrix[(i, j)] - mean[j]);
+        let covariance = centered.tr_mul(&centered) / matrix.nrows() as f64;
+
+        approx::assert_abs_diff_eq!(mean, *model.output_mean(), epsilon = 0.02);
+        approx::assert_abs_diff_eq!(covariance, model.output_covariance.matrix(), epsilon = 0.03);
+    }
+
+    #[test]
+    fn test_to_dense_matrix() {
+        let dataset = Dataset::new(vec![
+            MaskedSample::unmasked(dvector![1.0, 2.0]),
+            MaskedSample {
+                data: dvector![3.0, 4.0],
+                mask: Mask(BitVec::from_iter([false, true])),
+            },
+        ]);
+        assert_eq!(dataset.to_dense_matrix(-1.0), dmatrix![1.0, 2.0; -1.0, 4.0]);
+    }
+
+    #[test]
+    fn test_cross_validate_state_size() {
+        let (_, dataset) = PPCAModel::generate_synthetic(8, 2, 1_000, 0.5, 3);
+        let scores = PPCAModel::cross_validate_state_size(&dataset, &[1, 2, 3, 4], 5, 9);
+
+        assert_eq!(
+            scores.iter().map(|&(size, _)| size).collect::<Vec<_>>(),
+            vec![1, 2, 3, 4]
+        );
+        let best = scores
+            .iter()
+            .max_by(|(_, llk), (_, other_llk)| llk.total_cmp(other_llk))
+            .expect("there are candidates")
+            .0;
+        assert_eq!(best, 2);
+    }
+
+    #[test]
+    fn test_simulate_em_trajectory() {
+        let dataset = toy_model().sample(300, 0.0);
+        let trajectory = PPCAModel::init(2, &dataset).simulate_em_trajectory(&dataset, 100);
+        assert_eq!(trajectory.len(), 101);
+
+        for pair in trajectory.windows(2) {
+            assert!(pair[1].1 >= pair[0].1 - 1e-6 * pair[0].1.abs());
+        }
+        for (model, llk) in &trajectory {
+            approx::assert_relative_eq!(*llk, model.llk(&dataset), max_relative = 1e-10);
+        }
+
+        let step = |idx: usize| {
+            (trajectory[idx + 1].0.param_vector() - trajectory[idx].0.param_vector()).norm()
+        };
+        assert!(step(99) < 1e-3 * step(0));
+    }
+
+    #[test]
+    fn test_json_round_trip() {
+        use std::fs::File;
+        use std::io::{BufReader, BufWriter};
+
+        let model = toy_model();
+        let path = std::env::temp_dir().join(format!("ppca_model_{}.json", std::process::id()));
+        model
+            .to_json_writer(BufWriter::new(
+                File::create(&path).expect("can create file"),
+            ))
+            .expect("can write model");
+        let read =
+            PPCAModel::from_json_reader(BufReader::new(File::open(&path).expect("can open file")))
+                .expect("can read model");
+        std::fs::remove_file(&path).expect("can remove file");
+
+        approx::assert_relative_eq!(read.param_vector(), model.param_vector());
+        assert!(PPCAModel::from_json_reader("{\"not\": \"a model\"}".as_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_from_csv() {
+        let model = toy_model();
+        let dir = std::env::temp_dir();
+        let path = |name: &str| dir.join(format!("ppca_model_{}_{name}.csv", std::process::id()));
+        let (loadings_path, mean_path, noise_path) =
+            (path("loadings"), path("mean"), path("noise"));
+
+        let loadings = model
+            .loadings()
+            .row_iter()
+            .map(|row| row.iter().map(f64::to_string).collect::<Vec<_>>().join(","))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let mean = model
+            .output_mean()
+            .iter()
+            .map(f64::to_string)
+            .collect::<Vec<_>>()
+            .join("\n");
+        std::fs::write(&loadings_path, loadings).expect("can write file");
+        std::fs::write(&mean_path, mean).expect("can write file");
+        std::fs::write(&noise_path, model.noise_variance().to_string()).expect("can write file");
+
+        let read =
+            PPCAModel::from_csv(&loadings_path, &mean_path, &noise_path).expect("can read model");
+        assert_eq!(read.loadings(), model.loadings());
+        assert_eq!(read.output_mean(), model.output_mean());
+        approx::assert_relative_eq!(read.noise_variance(), model.noise_variance());
+
+        std::fs::write(&noise_path, "1.0,2.0").expect("can write file");
+        assert!(matches!(
+            PPCAModel::from_csv(&loadings_path, &mean_path, &noise_path),
+            Err(CsvError::UnexpectedShape { .. })
+        ));
+        std::fs::write(&noise_path, "-1.0").expect("can write file");
+        assert!(matches!(
+            PPCAModel::from_csv(&loadings_path, &mean_path, &noise_path),
+            Err(CsvError::Invalid(ValidationError::NonPositiveNoise(_)))
+        ));
+        std::fs::write(&mean_path, "0.0,1.0").expect("can write file");
+        std::fs::write(&noise_path, "0.01").expect("can write file");
+        assert!(matches!(
+            PPCAModel::from_csv(&loadings_path, &mean_path, &noise_path),
+            Err(CsvError::Invalid(
+                ValidationError::OutputSizeMismatch { .. }
+            ))
+        ));
+        std::fs::write(&mean_path, "0.0,one,0.0").expect("can write file");
+        assert!(matches!(
+            PPCAModel::from_csv(&loadings_path, &mean_path, &noise_path),
+            Err(CsvError::Parse { line: 1, .. })
+        ));
+
+        for path in [loadings_path, mean_path, noise_path] {
+            std::fs::remove_file(path).expect("can remove file");
+        }
+    }
+
+    #[test]
+    fn test_to_csv() {
+        let model = PPCAModel::new(
+            0.1f64.sqrt(),
+            dmatrix![
+                1.0 / 3.0, -2.5e-17, 0.0;
+                std::f64::consts::PI, 1e300, -7.0;
+            ]
+            .transpose(),
+            dvector![0.1, 0.2, -0.3],
+        );
+        let dir = std::env::temp_dir();
+        let path = |name: &str| dir.join(format!("ppca_to_csv_{}_{name}.csv", std::process::id()));
+        let (loadings_path, mean_path, noise_path) =
+            (path("loadings"), path("mean"), path("noise"));
+
+        model
+            .to_csv(&loadings_path, &mean_path, &noise_path)
+            .expect("can write model");
+
+        // Plain headerless tables with the same number of fields in every row, as expected
+        // by `pandas.read_csv(path, header=None)`.
+        for (path, n_rows, n_fields) in [
+            (&loadings_path, 3, 2),
+            (&mean_path, 3, 1),
+            (&noise_path, 1, 1),
+        ] {
+            let contents = std::fs::read_to_string(path).expect("can read file");
+            let lines = contents.lines().collect::<Vec<_>>();
+            assert_eq!(lines.len(), n_rows);
+            for line in lines {
+                assert_eq!(line.split(',').count(), n_fields);
+            }
+        }
+
+        let read =
+            PPCAModel::from_csv(&loadings_path, &mean_path, &noise_path).expect("can read model");
+        assert_eq!(read.loadings(), model.loadings());
+        assert_eq!(read.output_mean(), model.output_mean());
+        // Only the standard deviation is stored, so the variance may be off by a rounding.
+        approx::assert_relative_eq!(
+            read.noise_variance(),
+            model.noise_variance(),
+            max_relative = 1e-15
+        );
+
+        for path in [loadings_path, mean_path, noise_path] {
+            std::fs::remove_file(path).expect("can remove file");
+        }
+    }
+
+    #[test]
+    fn test_apply_affine_transform() {
+        let model = toy_model();
+        // A scaled rotation, for which the transformed noise stays isotropic.
+        let (cos, sin) = (0.6, 0.8);
+        let a = 2.0
+            * dmatrix![
+                cos, -sin, 0.0;
+                sin, cos, 0.0;
+                0.0, 0.0, 1.0;
+            ];
+        let b = dvector![1.0, -2.0, 0.5];
+        let transformed = model.sample(100, 0.0);
+        let lu = a.clone().lu();
+        let original = transformed
+            .iter()
+            .map(|sample| {
+                MaskedSample::unmasked(
+                    lu.solve(&(sample.data_vector() - &b))
+                        .expect("affine transform is invertible"),
+                )
+            })
+            .collect::<Dataset>();
+
+        let new_model = model
+            .apply_affine_transform(&a, &b)
+            .expect("transform is a scaled rotation");
+        let log_det = a.determinant().abs().ln();
+        approx::assert_relative_eq!(
+            new_model.llks(&original),
+            model.llks(&transformed).add_scalar(log_det),
+            max_relative = 1e-9
+        );
+        approx::assert_relative_eq!(new_model.noise_variance(), model.noise_variance() / 4.0);
+
+        // A non-uniform scaling would make the noise anisotropic.
+        let stretch = DMatrix::from_diagonal(&dvector![1.0, 2.0, 1.0]);
+        assert!(model.apply_affine_transform(&stretch, &b).is_err());
+        assert!(model
+            .apply_affine_transform(&DMatrix::zeros(3, 3), &b)
+            .is_err());
+    }
+
+    #[test]
+    fn test_apply_inverse_affine_transform() {
+        let model = toy_model();
+        let b = dvector![1.0, -2.0, 0.5];
+        let scaled_rotation = 0.5
+            * dmatrix![
+                0.0, 1.0, 0.0;
+                -1.0, 0.0, 0.0;
+                0.0, 0.0, 1.0;
+            ];
+
+        let round_trip = model
+            .apply_affine_transform(&scaled_rotation, &b)
+            .and_then(|transformed| {
+                transformed.apply_inverse_affine_transform(&scaled_rotation, &b)
+            })
+            .expect("transform is a scaled rotation");
+        approx::assert_relative_eq!(
+            round_trip.param_vector(),
+            model.param_vector(),
+            epsilon = 1e-12
+        );
+
+        let a = dmatrix![
+            2.0, 1.0, 0.0;
+            0.0, 1.0, -1.0;
+            1.0, 0.0, 3.0;
+        ];
+        assert!(model.apply_inverse_affine_transform(&a, &b).is_err());
+    }
+
+    #[test]
+    fn test_apply_function() {
+        let dataset = Dataset::new_with_weights(
+            vec![
+                MaskedSample::new(
+                    dvector![1.0, 2.5, -3.0],
+                    Mask(BitVec::from_iter([true, true, false])),
+                ),
+                MaskedSample::new(
+                    dvector![0.5, 7.0, 1e-3],
+                    Mask(BitVec::from_iter([false, true, true])),
+                ),
+            ],
+            vec![1.0, 2.0],
+        );
+
+        let logged = dataset.apply_function(f64::ln);
+        for (transformed, original) in logged.iter().zip(dataset.iter()) {
+            assert_eq!(transformed.mask().0, original.mask().0);
+            for (j, selected) in original.mask().0.iter().enumerate() {
+                if !selected {
+                    assert_eq!(transformed.data_vector()[j], original.data_vector()[j]);
+                }
+            }
+        }
+
+        let round_trip = logged.apply_function(f64::exp);
+        for (transformed, original) in round_trip.iter().zip(dataset.iter()) {
+            assert_eq!(transformed.mask().0, original.mask().0);
+            approx::assert_relative_eq!(
+                transformed.data_vector(),
+                original.data_vector(),
+                max_relative = 1e-12
+            );
+        }
+        assert_eq!(round_trip.weights, dataset.weights);
+    }
+
+    #[test]
+    fn test_project_to_dims() {
+        let model = toy_model();
+        let dataset = model.sample(100, 0.3);
+        let dims = [2, 0];
+
+        let projected = dataset
+            .project_to_dims(&dims)
+            .expect("dimensions are in bounds");
+        assert_eq!(projected.len(), dataset.len());
+        assert_eq!(projected.output_size(), Some(2));
+        for (projected_sample, sample) in projected.iter().zip(dataset.iter()) {
+            for (new_index, &index) in dims.iter().enumerate() {
+                assert_eq!(projected_sample.mask().0[new_index], sample.mask().0[index]);
+                if sample.mask().0[index] {
+                    assert_eq!(
+                        projected_sample.data_vector()[new_index],
+                        sample.data_vector()[index]
+                    );
+                }
+            }
+        }
+        assert_eq!(
+            dataset.project_to_dims(&[0, 3]).unwrap_err(),
+            IndexError {
+                index: 3,
+                output_size: 3
+            }
+        );
+
+        // Masking the other dimensions is the same as marginalizing them out.
+        let marginal = model.project_to_dims(&dims);
+        assert_eq!(marginal.output_size(), 2);
+        let masked = dataset
+            .iter()
+            .map(|sample| {
+                let mut mask = sample.mask().0.clone();
+                mask.set(1, false);
+                MaskedSample::new(sample.data_vector(), Mask(mask))
+            })
+            .collect::<Dataset>();
+        approx::assert_relative_eq!(
+            marginal.llks(&projected),
+            model.llks(&masked),
+            max_relative = 1e-9
+        );
+    }
+
+    #[test]
+    fn test_marginal_2d() {
+        let model = toy_model();
+        let covariance = model.output_covariance.matrix();
+
+        for (dim_i, dim_j) in [(0, 1), (2, 0), (1, 2)] {
+            let marginal = model.marginal_2d(dim_i, dim_j);
+            assert_eq!(marginal.output_size(), 2);
+            assert_eq!(marginal.state_size(), 1);
+
+            let dims = [dim_i, dim_j];
+            approx::assert_relative_eq!(
+                marginal.output_covariance.matrix(),
+                covariance.select_rows(&dims).select_columns(&dims),
+                epsilon = 1e-12,
+                max_relative = 1e-9
+            );
+            approx::assert_relative_eq!(marginal.mean, model.mean.select_rows(&dims));
+        }
+    }
+
+    #[test]
+    fn test_logdet_covariance() {
+        let models = [
+            toy_model(),
+            PPCAModel::new(
+                0.5,
+                dmatrix![
+                    2.0, -1.0, 0.5, 0.0;
+                ]
+                .transpose(),
+                DVector::zeros(4),
+            ),
+            // More hidden dimensions than output dimensions.
+            PPCAModel::new(
+                0.3,
+                dmatrix![
+                    1.0, 0.0, 2.0;
+                    0.5, 1.0, -1.0;
+                ],
+                DVector::zeros(2),
+            ),
+        ];
+
+        for model in models {
+            let covariance = model.loadings() * model.loadings().transpose()
+                + model.noise_variance()
+                    * DMatrix::<f64>::identity(model.output_size(), model.output_size());
+            let cholesky = covariance
+                .cholesky()
+                .expect("covariance is positive definite");
+            approx::assert_relative_eq!(
+                model.logdet_covariance(),
+                cholesky.determinant().ln(),
+                max_relative = 1e-10
+            );
+        }
+    }
+
+    #[test]
+    fn test_predictive_interval() {
+        let model = toy_model();
+        let dataset = model.sample_with_seed(1_000, 0.0, 0);
+        let mut rng = StdRng::seed_from_u64(1);
+
+        let mut n_covered = 0;
+        for sample in dataset.iter() {
+            let missing = rng.gen_range(0..3);
+            let mut mask = BitVec::from_elem(3, true);
+            mask.set(missing, false);
+            let masked = MaskedSample::new(sample.data_vector(), Mask(mask));
+
+            let (lower, upper) = model.predictive_interval(&masked, 0.05);
+            let value = sample.data_vector()[missing];
+            if lower[missing] <= value && value <= upper[missing] {
+                n_covered += 1;
+            }
+            for observed in (0..3).filter(|&j| j != missing) {
+                assert_eq!(lower[observed], sample.data_vector()[observed]);
+                assert_eq!(upper[observed], sample.data_vector()[observed]);
+            }
+        }
+
+        // The standard deviation of the count is about 7.
+        assert!((920..=980).contains(&n_covered), "{n_covered} covered");
+    }
+
+    #[test]
+    fn test_imputation_uncertainty_ratio() {
+        let model = toy_model();
+        for sample in model.sample(100, 0.5).iter() {
+            let ratio = model.imputation_uncertainty_ratio(sample);
+            assert_eq!(ratio.len(), 3);
+            for (j, selected) in sample.mask().0.iter().enumerate() {
+                assert!(ratio[j] >= 0.0 && ratio[j] <= 1.0 + 1e-12, "{}", ratio[j]);
+                if selected {
+                    assert_eq!(ratio[j], 0.0);
+                }
+            }
+        }
+
+        let empty = MaskedSample::new(dvector![0.0, 0.0, 0.0], Mask(BitVec::from_elem(3, false)));
+        approx::assert_relative_eq!(
+            model.imputation_uncertainty_ratio(&empty),
+            DVector::repeat(3, 1.0),
+            max_relative = 1e-12
+        );
+
+        // The first dimension is the sum of the other two, up to the noise.
+        let partial = MaskedSample::from_partial(3, &[1, 2], &dvector![1.0, 0.5]);
+        assert!(model.imputation_uncertainty_ratio(&partial)[0] < 0.05);
+    }
+
+    #[test]
+    fn test_validate() {
+        assert_eq!(toy_model().validate(), Ok(()));
+
+        let mut model = toy_model();
+        model.mean = dvector![0.0, 1.0];
+        assert_eq!(
+            model.validate(),
+            Err(ValidationError::OutputSizeMismatch {
+                transform_rows: 3,
+                mean_len: 2
+            })
+        );
+
+        let mut model = toy_model();
+        model.output_covariance.isotropic_noise = 0.0;
+        assert_eq!(
+            model.validate(),
+            Err(ValidationError::NonPositiveNoise(0.0))
+        );
+
+        // A negative standard deviation gives a positive variance, but is still invalid.
+        let mut model = toy_model();
+        model.output_covariance.isotropic_noise = -0.1;
+        assert_eq!(
+            model.validate(),
+            Err(ValidationError::NonPositiveNoise(-0.1))
+        );
+
+        let mut model = toy_model();
+        model.output_covariance.isotropic_noise = f64::NAN;
+        assert!(matches!(
+            model.validate(),
+            Err(ValidationError::NonPositiveNoise(_))
+        ));
+
+        let mut model = toy_model();
+        model.output_covariance.transform.to_mut()[(1, 0)] = f64::INFINITY;
+        assert_eq!(
+            model.validate(),
+            Err(ValidationError::NonFinite("transform"))
+        );
+
+        let mut model = toy_model();
+        model.mean[2] = f64::NAN;
+        assert_eq!(model.validate(), Err(ValidationError::NonFinite("mean")));
+    }
+
+    #[test]
+    fn test_dataset_validate() {
+        let dataset = toy_model().sample(10, 0.2);
+        assert_eq!(dataset.validate(), Ok(()));
+        assert_eq!(Dataset::new(vec![]).validate(), Ok(()));
+
+        let ragged = Dataset::new(vec![
+            MaskedSample::unmasked(dvector![1.0, 2.0, 3.0]),
+            MaskedSample::unmasked(dvector![1.0, 2.0, 3.0]),
+            MaskedSample::unmasked(dvector![1.0, 2.0]),
+        ]);
+        assert_eq!(
+            ragged.validate(),
+            Err(DatasetError::DimensionMismatch {
+                sample: 2,
+                expected: 3,
+                found: 2
+            })
+        );
+
+        let mut negative = dataset.clone();
+        negative.weights[3] = -1.0;
+        assert_eq!(
+            negative.validate(),
+            Err(DatasetError::InvalidWeight {
+                sample: 3,
+                weight: -1.0
+            })
+        );
+
+        let zero = dataset.with_weights(vec![0.0; 10]);
+        assert_eq!(zero.validate(), Err(DatasetError::InvalidTotalWeight(0.0)));
+
+        let model = toy_model();
+        approx::assert_relative_eq!(
+            model.try_llks(&dataset).expect("dataset is valid"),
+            model.llks(&dataset)
+        );
+        assert!(model.try_llks(&ragged).is_err());
+        let wrong_size = Dataset::new(vec![MaskedSample::unmasked(dvector![1.0, 2.0])]);
+        assert_eq!(
+            model.try_llks(&wrong_size),
+            Err(DatasetError::DimensionMismatch {
+                sample: 0,
+                expected: 3,
+                found: 2
+            })
+        );
+
+        approx::assert_relative_eq!(
+            model.try_llk(&dataset).expect("dataset is valid"),
+            model.llk(&dataset)
+        );
+        assert_eq!(
+            model
+                .try_smooth(&dataset)
+                .expect("dataset is valid")
+                .data
+                .len(),
+            dataset.len()
+        );
+        for dataset in [&ragged, &wrong_size] {
+            assert!(model.try_llk(dataset).is_err());
+            assert!(model.try_infer(dataset).is_err());
+            assert!(model.try_smooth(dataset).is_err());
+            assert!(model.try_extrapolate(dataset).is_err());
+        }
+    }
+
+    #[test]
+    fn test_noise_accessors() {
+        let model = toy_model();
+        approx::assert_relative_eq!(model.noise_std(), model.noise_variance().sqrt());
+        approx::assert_relative_eq!(model.log_noise_variance(), model.noise_variance().ln());
+
+        let model = model.set_noise_std(0.5);
+        approx::assert_relative_eq!(model.noise_variance(), 0.25);
+        approx::assert_relative_eq!(model.noise_std(), 0.5);
+        approx::assert_relative_eq!(model.log_noise_variance(), 0.25f64.ln());
+    }
+
+    #[test]
+    fn test_dimension_importance() {
+        let model = PPCAModel::new(
+            0.1,
+            dmatrix![1.0, 0.0, 2.0, 0.1].transpose(),
+            DVector::zeros(4),
+        );
+        let importance = model.dimension_importance();
+        approx::assert_relative_eq!(
+            importance,
+            dvector![1.0 / 1.01, 0.0, 4.0 / 4.01, 0.01 / 0.02],
+            max_relative = 1e-12
+        );
+        assert_eq!(model.top_k_dimensions(2), vec![2, 0]);
+        assert_eq!(model.top_k_dimensions(4), vec![2, 0, 3, 1]);
+    }
+
+    #[test]
+    fn test_expected_observation() {
+        let model = toy_model();
+        assert_eq!(model.expected_observation(), *model.output_mean());
+
+        // Observing nothing leaves the marginal mean unchanged.
+        let empty = MaskedSample {
+            data: dvector![0.0, 0.0, 0.0],
+            mask: Mask(BitVec::from_elem(3, false)),
+        };
+        approx::assert_relative_eq!(
+            model.extrapolate_one(&empty).data_vector(),
+            model.expected_observation()
+        );
+    }
+
+    #[test]
+    fn test_sample_prior() {
+        let model = toy_model();
+        let matrix = model.sample_prior(100_000, 17).to_dense_matrix(0.0);
+        let mean = matrix.row_mean().transpose();
+        let centered = DMatrix::from_fn(matrix.nrows(), 3, |i, j| matrix[(i, j)] - mean[j]);
+        let covariance = centered.tr_mul(&centered) / matrix.nrows() as f64;
+
+        approx::assert_abs_diff_eq!(mean, *model.output_mean(), epsilon = 0.02);
+        approx::assert_abs_diff_eq!(covariance, model.output_covariance.matrix(), epsilon = 0.03);
+    }
+
+    #[test]
+    fn test_observe_and_update() {
+        let real_model = toy_model();
+        let dataset = real_model.sample_with_seed(5_000, 0.2, 7);
+        let mut fitted = PPCAModel::init_with_rng(2, &dataset, &mut StdRng::seed_from_u64(7));
+        for _ in 0..100 {
+            fitted = fitted.iterate(&dataset);
+        }
+
+        let mut model = PPCAModel::new(1.0, real_model.loadings().clone(), DVector::zeros(3));
+        for sample in dataset.iter() {
+            model = model.observe_and_update(sample, 200.0);
+        }
+
+        approx::assert_abs_diff_eq!(*model.output_mean(), *fitted.output_mean(), epsilon = 0.2);
+        approx::assert_relative_eq!(
+            model.noise_variance(),
+            fitted.noise_variance(),
+            max_relative = 0.5
+        );
+    }
+
+    #[test]
+    fn test_expected_complete_data_llk() {
+        let dataset = toy_model().sample(300, 0.2);
+        let mut model = PPCAModel::init(2, &dataset);
+        for _ in 0..3 {
+            let iterated = model.iterate(&dataset);
+            let q_old = model.expected_complete_data_llk(&dataset, &model);
+            let q_new = iterated.expected_complete_data_llk(&dataset, &model);
+            assert!(q_new >= q_old - 1e-6 * q_old.abs(), "{q_new} < {q_old}");
+            model = iterated;
+        }
+
+        // `log p(x) = Q(θ | θ) + H[p(z | x, θ)]`.
+        let posterior_entropy = dataset
+            .iter()
+            .map(|sample| {
+                let covariance = model.infer_one(sample).covariance().clone();
+                (2.0 * (1.0 + LN_2PI) + covariance.determinant().ln()) / 2.0
+            })
+            .sum::<f64>();
+        approx::assert_relative_eq!(
+            model.expected_complete_data_llk(&dataset, &model) + posterior_entropy,
+            model.llk(&dataset),
+            max_relative = 1e-8
+        );
+    }
+
+    #[test]
+    fn test_rotate_to_pca() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let model = PPCAModel::new(
+            0.3,
+            standard_noise_matrix_with_rng(6, 3, &mut rng),
+            standard_noise_with_rng(6, &mut rng),
+        );
+        let rotated = model.rotate_to_pca();
+        assert_eq!(rotated.state_size(), 3);
+
+        let gram = rotated.loadings().transpose() * rotated.loadings();
+        for i in 0..3 {
+            for j in 0..3 {
+                if i != j {
+                    approx::assert_abs_diff_eq!(gram[(i, j)], 0.0, epsilon = 1e-10);
+                }
+            }
+        }
+        let norms = rotated
+            .loadings()
+            .column_iter()
+            .map(|column| column.norm())
+            .collect::<Vec<_>>();
+        assert!(norms.windows(2).all(|pair| pair[0] >= pair[1]), "{norms:?}");
+
+        let dataset = model.sample(100, 0.2);
+        approx::assert_relative_eq!(
+            rotated.llks(&dataset),
+            model.llks(&dataset),
+            max_relative = 1e-10
+        );
+
+        assert_eq!(rotated.loadings(), model.to_canonical().loadings());
+
+        // A bigger state than the output gets zero columns at the end.
+        let wide = PPCAModel::new(
+            0.3,
+            standard_noise_matrix_with_rng(2, 3, &mut rng),
+            DVector::zeros(2),
+        );
+        let canonical = wide.to_canonical();
+        assert_eq!(canonical.state_size(), 3);
+        assert_eq!(canonical.loadings().column(2), DVector::zeros(2));
+        let dataset = wide.sample(100, 0.2);
+        approx::assert_relative_eq!(
+            canonical.llks(&dataset),
+            wide.llks(&dataset),
+            max_relative = 1e-10
+        );
+    }
+
+    #[test]
+    fn test_check_gradient() {
+        let dataset = toy_model().sample_with_seed(200, 0.2, 0);
+        let model = PPCAModel::init_with_rng(2, &dataset, &mut StdRng::seed_from_u64(0));
+        let error = model.check_gradient(&dataset, 1e-5);
+        assert!(error < 1e-5, "gradient relative error is {error}");
+
+        // Also near the optimum, where the gradient is small.
+        let mut fitted = model;
+        for _ in 0..50 {
+            fitted = fitted.iterate(&dataset);
+        }
+        let error = fitted.check_gradient(&dataset, 1e-5);
+        assert!(error < 1e-5, "gradient relative error is {error}");
+    }
+
+    #[test]
+    fn test_hessian_neg_llk() {
+        let dataset = toy_model().sample(1_000, 0.0);
+        let mut model = PPCAModel::init(1, &dataset);
+        for _ in 0..200 {
+            model = model.iterate(&dataset);
+        }
+
+        let hessian = model.hessian_neg_llk(&dataset, 1e-5);
+        assert_eq!(hessian.shape(), (7, 7));
+        approx::assert_relative_eq!(hessian, hessian.transpose());
+
+        let eigenvalues = hessian.symmetric_eigen().eigenvalues;
+        assert!(eigenvalues.min() > -1e-6 * eigenvalues.max());
+    }
+
+    #[test]
+    fn test_update_from_gradient() {
+        let dataset = toy_model().sample(200, 0.2);
+        let model = PPCAModel::init(2, &dataset);
+        let updated = model.update_from_gradient(&dataset, 1e-4);
+        assert!(updated.llk(&dataset) > model.llk(&dataset));
+    }
+
+    #[test]
+    fn test_update_from_gradient_adam() {
+        let dataset = toy_model().sample(200, 0.2);
+        let initial = PPCAModel::init(2, &dataset);
+        let n_params = initial.param_vector().len();
+        let mut m = DVector::zeros(n_params);
+        let mut v = DVector::zeros(n_params);
+
+        let mut model = initial.clone();
+        for t in 1..=50 {
+            model = model
+                .update_from_gradient_adam(&dataset, 1e-2, 0.9, 0.999, 1e-8, &mut m, &mut v, t);
+        }
+
+        assert!(model.llk(&dataset) > initial.llk(&dataset));
+    }
+
+    #[test]
+    fn test_entropy() {
+        approx::assert_relative_eq!(toy_model().entropy(), 2.510176, epsilon = 1e-4);
+    }
+
+    #[test]
+    fn test_log_normalizer() {
+        let model = toy_model();
+        let at_mean = MaskedSample::unmasked(model.output_mean().clone());
+        approx::assert_relative_eq!(model.llk_one(&at_mean), -model.log_normalizer());
+
+        // Monte Carlo estimate of `∫ p(x) log p(x) dx = -d/2 - log_normalizer`.
+        let dataset = model.sample(100_000, 0.0);
+        let average_llk = model.llk(&dataset) / dataset.len() as f64;
+        approx::assert_abs_diff_eq!(average_llk, -1.5 - model.log_normalizer(), epsilon = 0.03);
+    }
+
     #[test]
     fn test_llk() {
         let model = toy_model();
@@ -21,6 +21,44 @@ pub fn ppca_rs(_py: Python<'_>, m: &PyModule) -> PyResult<()> {
     Ok(())
 }
 
+/// Reads the rows of a 2D array as samples, with non-finite values (`np.nan`) as
+/// missing.
+fn dataset_from_ndarray(py: Python, ndarray: PyReadonlyArray2<f64>) -> Dataset {
+    let n_samples = ndarray.shape()[0];
+    let output_size = ndarray.shape()[1];
+    let array_view = ndarray.as_array();
+    let iter_sample =
+        |sample_id| (0..output_size).map(move |dimension| array_view[(sample_id, dimension)]);
+
+    let data = py.allow_threads(|| {
+        (0..n_samples)
+            .map(|sample_id| {
+                let data = iter_sample(sample_id).collect::<Vec<_>>().into();
+                let mask = iter_sample(sample_id)
+                    .map(f64::is_finite)
+                    .collect::<BitVec>();
+                MaskedSample::new(data, Mask(mask))
+            })
+            .collect()
+    });
+
+    Dataset::new(data)
+}
+
+/// Writes the samples as the rows of a 2D array, with `np.nan` for the missing values.
+fn dataset_to_ndarray(py: Python, dataset: &Dataset) -> Py<PyArray2<f64>> {
+    let rows = py.allow_threads(|| {
+        dataset
+            .data
+            .par_iter()
+            .map(MaskedSample::masked_vector)
+            .collect::<Vec<_>>()
+    });
+
+    let matrix = DMatrix::from_columns(&rows).transpose();
+    matrix.to_pyarray(py).to_owned()
+}
+
 #[pyclass]
 #[pyo3(name = "Dataset")]
 struct DatasetWrapper(Dataset);
@@ -29,44 +67,21 @@ struct DatasetWrapper(Dataset);
 impl DatasetWrapper {
     #[new]
     fn new(py: Python, ndarray: PyReadonlyArray2<f64>) -> PyResult<DatasetWrapper> {
-        let n_samples = ndarray.shape()[0];
-        let output_size = ndarray.shape()[1];
-        let array_view = ndarray.as_array();
-        let iter_sample =
-            |sample_id| (0..output_size).map(move |dimension| array_view[(sample_id, dimension)]);
-
-        let data = py.allow_threads(|| {
-            (0..n_samples)
-                .map(|sample_id| {
-                    let data = iter_sample(sample_id).collect::<Vec<_>>().into();
-                    let mask = iter_sample(sample_id)
-                        .map(f64::is_finite)
-                        .collect::<BitVec>();
-                    MaskedSample::new(data, Mask(mask))
-                })
-                .collect()
-        });
-
-        Ok(DatasetWrapper(Dataset::new(data)))
+        Ok(DatasetWrapper(dataset_from_ndarray(py, ndarray)))
     }
 
     fn numpy(&self, py: Python) -> Py<PyArray2<f64>> {
-        let rows = py.allow_threads(|| {
-            self.0
-                .data
-                .par_iter()
-                .map(MaskedSample::masked_vector)
-                .collect::<Vec<_>>()
-        });
-
-        let matrix = DMatrix::from_columns(&rows).transpose();
-        matrix.to_pyarray(py).to_owned()
+        dataset_to_ndarray(py, &self.0)
     }
 
     fn __len__(&self) -> usize {
         self.0.len()
     }
 
+    fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
     fn output_size(&self) -> Option<usize> {
         self.0.output_size()
     }
@@ -74,6 +89,22 @@ impl DatasetWrapper {
     fn empty_dimensions(&self) -> Vec<usize> {
         self.0.empty_dimensions()
     }
+
+    fn with_mask_probability(&self, mask_probability: f64, seed: u64) -> DatasetWrapper {
+        DatasetWrapper(self.0.with_mask_probability(mask_probability, seed))
+    }
+
+    fn drop_missing(&self) -> DatasetWrapper {
+        DatasetWrapper(self.0.drop_missing())
+    }
+
+    fn n_fully_observed(&self) -> usize {
+        self.0.n_fully_observed()
+    }
+
+    fn n_has_missing(&self) -> usize {
+        self.0.n_has_missing()
+    }
 }
 
 #[pyclass]
@@ -302,6 +333,11 @@ impl PPCAModelWrapper {
         self.0.output_covariance().isotropic_noise
     }
 
+    #[getter]
+    fn noise_variance(&self) -> f64 {
+        self.0.noise_variance()
+    }
+
     #[getter]
     fn mean(&self, py: Python<'_>) -> Py<PyArray1<f64>> {
         self.0
@@ -352,8 +388,17 @@ impl PPCAModelWrapper {
         }
     }
 
-    fn smooth(&self, py: Python<'_>, dataset: &DatasetWrapper) -> DatasetWrapper {
-        py.allow_threads(|| DatasetWrapper(self.0.smooth(&dataset.0)))
+    fn smooth(&self, py: Python<'_>, dataset: &PyAny) -> PyResult<PyObject> {
+        if let Ok(dataset) = dataset.extract::<PyRef<DatasetWrapper>>() {
+            let dataset = &dataset.0;
+            return Ok(py
+                .allow_threads(|| DatasetWrapper(self.0.smooth(dataset)))
+                .into_py(py));
+        }
+
+        let dataset = dataset_from_ndarray(py, dataset.extract()?);
+        let smoothed = py.allow_threads(|| self.0.smooth(&dataset));
+        Ok(dataset_to_ndarray(py, &smoothed).into_py(py))
     }
 
     fn extrapolate(&self, py: Python<'_>, dataset: &DatasetWrapper) -> DatasetWrapper {
@@ -364,6 +409,27 @@ impl PPCAModelWrapper {
         py.allow_threads(|| PPCAModelWrapper(self.0.iterate(&dataset.0)))
     }
 
+    fn fit(
+        &self,
+        py: Python<'_>,
+        dataset: &DatasetWrapper,
+        n_iters: usize,
+    ) -> (PPCAModelWrapper, Vec<f64>) {
+        py.allow_threads(|| {
+            let mut model = self.0.clone();
+            let mut llks = Vec::with_capacity(n_iters + 1);
+
+            for _ in 0..n_iters {
+                llks.push(model.llk(&dataset.0));
+                model = model.iterate(&dataset.0);
+            }
+
+            llks.push(model.llk(&dataset.0));
+
+            (PPCAModelWrapper(model), llks)
+        })
+    }
+
     fn to_canonical(&self, py: Python<'_>) -> PPCAModelWrapper {
         py.allow_threads(|| PPCAModelWrapper(self.0.to_canonical()))
     }
@@ -426,6 +492,24 @@ impl PPCAMixWrapper {
         py.allow_threads(|| PPCAMixWrapper(PPCAMix::init(n_models, state_size, &dataset.0)))
     }
 
+    #[staticmethod]
+    fn new_with_state_size(
+        py: Python,
+        dataset: &DatasetWrapper,
+        n_components: usize,
+        state_size: usize,
+        seed: u64,
+    ) -> PPCAMixWrapper {
+        py.allow_threads(|| {
+            PPCAMixWrapper(PPCAMix::new_with_state_size(
+                &dataset.0,
+                n_components,
+                state_size,
+                seed,
+            ))
+        })
+    }
+
     #[staticmethod]
     fn load(bytes: &PyBytes) -> PyResult<PPCAMixWrapper> {
         Ok(PPCAMixWrapper(
@@ -446,6 +530,11 @@ impl PPCAMixWrapper {
         self.0.output_size()
     }
 
+    #[getter]
+    fn n_components(&self) -> usize {
+        self.0.n_components()
+    }
+
     #[getter]
     fn state_sizes(&self) -> Vec<usize> {
         self.0.state_sizes()
@@ -504,24 +593,99 @@ impl PPCAMixWrapper {
             .to_owned()
     }
 
+    pub fn hard_cluster_assignments(&self, py: Python, dataset: &DatasetWrapper) -> Vec<usize> {
+        py.allow_threads(|| self.0.hard_cluster_assignments(&dataset.0))
+    }
+
+    pub fn evaluate_on_grid(
+        &self,
+        py: Python,
+        x_range: (f64, f64),
+        y_range: (f64, f64),
+        n_points: usize,
+    ) -> (Py<PyArray2<f64>>, Py<PyArray2<f64>>, Py<PyArray2<f64>>) {
+        let (x_grid, y_grid, log_densities) =
+            py.allow_threads(|| self.0.evaluate_on_grid(x_range, y_range, n_points));
+        (
+            x_grid.to_pyarray(py).to_owned(),
+            y_grid.to_pyarray(py).to_owned(),
+            log_densities.to_pyarray(py).to_owned(),
+        )
+    }
+
     fn infer(&self, py: Python<'_>, dataset: &DatasetWrapper) -> InferredMaskedMixBatch {
         InferredMaskedMixBatch {
             data: py.allow_threads(|| self.0.infer(&dataset.0)),
         }
     }
 
-    pub fn smooth(&self, py: Python, dataset: &DatasetWrapper) -> DatasetWrapper {
-        DatasetWrapper(py.allow_threads(|| self.0.smooth(&dataset.0)))
+    pub fn smooth(&self, py: Python, dataset: &PyAny) -> PyResult<PyObject> {
+        if let Ok(dataset) = dataset.extract::<PyRef<DatasetWrapper>>() {
+            let dataset = &dataset.0;
+            return Ok(DatasetWrapper(py.allow_threads(|| self.0.smooth(dataset))).into_py(py));
+        }
+
+        let dataset = dataset_from_ndarray(py, dataset.extract()?);
+        let smoothed = py.allow_threads(|| self.0.smooth(&dataset));
+        Ok(dataset_to_ndarray(py, &smoothed).into_py(py))
     }
 
     pub fn extrapolate(&self, py: Python, dataset: &DatasetWrapper) -> DatasetWrapper {
         DatasetWrapper(py.allow_threads(|| self.0.extrapolate(&dataset.0)))
     }
 
+    pub fn impute_missing(&self, py: Python, dataset: &DatasetWrapper) -> DatasetWrapper {
+        DatasetWrapper(py.allow_threads(|| self.0.impute_missing(&dataset.0)))
+    }
+
     pub fn iterate(&self, py: Python, dataset: &DatasetWrapper) -> PPCAMixWrapper {
         PPCAMixWrapper(py.allow_threads(|| self.0.iterate(&dataset.0)))
     }
 
+    pub fn fit(
+        &self,
+        py: Python,
+        dataset: &DatasetWrapper,
+        n_iters: usize,
+    ) -> (PPCAMixWrapper, Vec<f64>) {
+        py.allow_threads(|| {
+            let mut model = self.0.clone();
+            let mut llks = Vec::with_capacity(n_iters + 1);
+
+            for _ in 0..n_iters {
+                llks.push(model.llk(&dataset.0));
+                model = model.iterate(&dataset.0);
+            }
+
+            llks.push(model.llk(&dataset.0));
+
+            (PPCAMixWrapper(model), llks)
+        })
+    }
+
+    pub fn fit_with_restarts(
+        &self,
+        py: Python,
+        dataset: &DatasetWrapper,
+        n_restarts: usize,
+        max_iter: usize,
+        tol: f64,
+        seed: u64,
+    ) -> PPCAMixWrapper {
+        PPCAMixWrapper(py.allow_threads(|| {
+            self.0
+                .fit_with_restarts(&dataset.0, n_restarts, max_iter, tol, seed)
+        }))
+    }
+
+    pub fn reorder_components_by_mean(&self) -> PPCAMixWrapper {
+        PPCAMixWrapper(self.0.reorder_components_by_mean())
+    }
+
+    pub fn reorder_components_by_weight(&self) -> PPCAMixWrapper {
+        PPCAMixWrapper(self.0.reorder_components_by_weight())
+    }
+
     pub fn to_canonical(&self, py: Python) -> PPCAMixWrapper {
         PPCAMixWrapper(py.allow_threads(|| self.0.to_canonical()))
     }
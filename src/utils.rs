@@ -1,25 +1,27 @@
 use bit_vec::BitVec;
 use nalgebra::{DMatrix, DVector};
 use rand::distributions::Distribution;
+use rand::Rng;
 use rand_distr::StandardNormal;
 
-pub(crate) fn standard_noise(size: usize) -> DVector<f64> {
+pub(crate) fn standard_noise_with_rng<R: Rng + ?Sized>(size: usize, rng: &mut R) -> DVector<f64> {
     DVector::from(
         StandardNormal
-            .sample_iter(rand::thread_rng())
+            .sample_iter(rng)
             .take(size)
             .collect::<Vec<f64>>(),
     )
 }
 
-pub(crate) fn standard_noise_matrix(rows: usize, cols: usize) -> DMatrix<f64> {
+pub(crate) fn standard_noise_matrix_with_rng<R: Rng + ?Sized>(
+    rows: usize,
+    cols: usize,
+    rng: &mut R,
+) -> DMatrix<f64> {
     DMatrix::from_vec(
         rows,
         cols,
-        StandardNormal
-            .sample_iter(rand::thread_rng())
-            .take(rows * cols)
-            .collect(),
+        StandardNormal.sample_iter(rng).take(rows * cols).collect(),
     )
 }
 
@@ -110,6 +112,24 @@ impl Mask {
         expanded
     }
 
+    pub(crate) fn expand_rows(&self, matrix: &DMatrix<f64>) -> DMatrix<f64> {
+        let mut it = matrix.row_iter();
+        let mut expanded = DMatrix::zeros(self.0.len(), matrix.ncols());
+
+        for (mut row, selected) in expanded.row_iter_mut().zip(&self.0) {
+            if selected {
+                row.copy_from(
+                    &it.next()
+                        .expect("input matrix too short in row size for mask"),
+                );
+            }
+        }
+
+        assert!(it.next().is_none(), "input matrix has more rows than mask");
+
+        expanded
+    }
+
     pub(crate) fn fillna(&self, vector: &DVector<f64>) -> DVector<f64> {
         vector
             .data